@@ -86,4 +86,5 @@ fn main() {
 
     println!("cargo:rerun-if-changed=cpp/bindings.cpp");
     println!("cargo:rerun-if-changed=cpp/bindings.h");
+    println!("cargo:rerun-if-changed=cpp/codecs");
 }
@@ -1,18 +1,32 @@
 #include "bindings.h"
 
 #include "prt/API.h"
+#include "prt/Annotation.h"
+#include "prt/DecoderInfo.h"
 #include "prt/EncoderInfo.h"
 #include "prt/ResolveMap.h"
 
+#include "prtx/DataBackend.h"
+#include "prtx/Decoder.h"
+#include "prtx/Exception.h"
+#include "prtx/ExtensionManager.h"
+#include "prtx/URI.h"
+
+#include <algorithm>
+#include <atomic>
+#include <cwchar>
 #include <filesystem>
 #include <map>
+#include <sstream>
 #include <string>
+#include <utility>
 #include <vector>
 
 namespace {
 
 using AttributeMapBuilderUPtr = std::unique_ptr<prt::AttributeMapBuilder, PRTObjectDestroyer>;
 using AttributeMapUPtr = std::unique_ptr<const prt::AttributeMap, PRTObjectDestroyer>;
+using DecoderInfoUPtr = std::unique_ptr<const prt::DecoderInfo, PRTObjectDestroyer>;
 using EncoderInfoUPtr = std::unique_ptr<const prt::EncoderInfo, PRTObjectDestroyer>;
 using InitialShapeBuilderUPtr = std::unique_ptr<prt::InitialShapeBuilder, PRTObjectDestroyer>;
 using InitialShapeUPtr = std::unique_ptr<const prt::InitialShape, PRTObjectDestroyer>;
@@ -40,16 +54,153 @@ std::wstring toUTF16FromUTF8(const std::string& utf8String) {
 	return callAPI<char, wchar_t>(prt::StringUtils::toUTF16FromUTF8, utf8String);
 }
 
+prt::Status createValidatedOptionsAndStates(const wchar_t* encID, const prt::AttributeMap* unvalidatedOptions,
+                                            AttributeMapUPtr& validatedOptions, AttributeMapUPtr& optionStates) {
+	prt::Status status = prt::STATUS_OK;
+	const EncoderInfoUPtr encInfo(prt::createEncoderInfo(encID, &status));
+	if (!encInfo)
+		return (status != prt::STATUS_OK) ? status : prt::STATUS_ENCODER_NOT_FOUND;
+	const prt::AttributeMap* validated = nullptr;
+	const prt::AttributeMap* states = nullptr;
+	status = encInfo->createValidatedOptionsAndStates(unvalidatedOptions, &validated, &states);
+	validatedOptions.reset(validated);
+	optionStates.reset(states);
+	return status;
+}
+
 const prt::AttributeMap* createValidatedOptions(const wchar_t* encID,
                                                 const prt::AttributeMap* unvalidatedOptions = nullptr) {
-	const EncoderInfoUPtr encInfo(prt::createEncoderInfo(encID));
-	const prt::AttributeMap* validatedOptions = nullptr;
-	const prt::AttributeMap* optionStates = nullptr;
-	const prt::Status s =
-	        encInfo->createValidatedOptionsAndStates(unvalidatedOptions, &validatedOptions, &optionStates);
-	if (optionStates != nullptr)
-		optionStates->destroy();
-	return (s == prt::STATUS_OK) ? validatedOptions : nullptr;
+	AttributeMapUPtr validatedOptions;
+	AttributeMapUPtr optionStates;
+	const prt::Status s = createValidatedOptionsAndStates(encID, unvalidatedOptions, validatedOptions, optionStates);
+	return (s == prt::STATUS_OK) ? validatedOptions.release() : nullptr;
+}
+
+void visitAttributeMap(const prt::AttributeMap* am, AbstractAttributeMapBinding* binding) {
+	if (am == nullptr || binding == nullptr)
+		return;
+
+	size_t keyCount = 0;
+	const wchar_t* const* keys = am->getKeys(&keyCount);
+	for (size_t k = 0; k < keyCount; k++) {
+		const wchar_t* key = keys[k];
+		PrimitiveWrapper value{};
+		value.type = am->getType(key);
+
+		// owners of the converted strings
+		std::string stringValue;
+		std::vector<std::string> stringValues;
+		std::vector<const char*> stringValuePtrs;
+
+		switch (am->getType(key)) {
+			case prt::AttributeMap::PT_STRING:
+				stringValue = toUTF8FromUTF16(am->getString(key));
+				value.stringValue = stringValue.c_str();
+				break;
+			case prt::AttributeMap::PT_FLOAT:
+				value.floatValue = am->getFloat(key);
+				break;
+			case prt::AttributeMap::PT_BOOL:
+				value.boolValue = am->getBool(key);
+				break;
+			case prt::AttributeMap::PT_INT:
+				value.intValue = am->getInt(key);
+				break;
+			case prt::AttributeMap::PT_STRING_ARRAY: {
+				const wchar_t* const* strings = am->getStringArray(key, &value.count, &value.rows);
+				for (size_t i = 0; i < value.count; i++)
+					stringValues.emplace_back(toUTF8FromUTF16(strings[i]));
+				for (const auto& sv : stringValues)
+					stringValuePtrs.push_back(sv.c_str());
+				value.stringArray = stringValuePtrs.data();
+				break;
+			}
+			case prt::AttributeMap::PT_FLOAT_ARRAY:
+				value.floatArray = am->getFloatArray(key, &value.count, &value.rows);
+				break;
+			case prt::AttributeMap::PT_BOOL_ARRAY:
+				value.boolArray = am->getBoolArray(key, &value.count, &value.rows);
+				break;
+			case prt::AttributeMap::PT_INT_ARRAY:
+				value.intArray = am->getIntArray(key, &value.count, &value.rows);
+				break;
+			default:
+				value.type = prt::AttributeMap::PT_UNDEFINED;
+				break;
+		}
+
+		const std::string nKey = toUTF8FromUTF16(key);
+		binding->add_attribute(binding->context, nKey.c_str(), &value);
+	}
+}
+
+std::vector<std::wstring> tokenize(const std::wstring& input, wchar_t delimiter) {
+	std::vector<std::wstring> tokens;
+	size_t start = 0;
+	while (start <= input.size()) {
+		const size_t end = std::min(input.find(delimiter, start), input.size());
+		if (end > start)
+			tokens.emplace_back(input.substr(start, end - start));
+		start = end + 1;
+	}
+	return tokens;
+}
+
+template <typename FUNC>
+std::vector<std::wstring> listExtensionIds(FUNC listIds) {
+	std::vector<wchar_t> buffer(4096, L'\0');
+	size_t size = buffer.size();
+	listIds(buffer.data(), &size, nullptr);
+	if (size > buffer.size()) {
+		buffer.resize(size, L'\0');
+		listIds(buffer.data(), &size, nullptr);
+	}
+	return tokenize(buffer.data(), L';');
+}
+
+// the decoder PRT picks for the (resolved) URI, i.e. the one with the highest merit which can handle it
+std::wstring findDecoderId(const std::wstring& keyOrUri, const prt::ResolveMap* resolveMap) {
+	std::wstring uri = keyOrUri;
+	if (resolveMap != nullptr && resolveMap->hasKey(keyOrUri.c_str()))
+		uri = resolveMap->getString(keyOrUri.c_str());
+
+	try {
+		const prtx::DecoderPtr decoder =
+		        prtx::ExtensionManager::instance().createDecoder(prtx::URI::create(uri), prt::CT_GEOMETRY);
+		return decoder ? decoder->getID() : std::wstring();
+	}
+	catch (const std::exception&) {
+		return {}; // e.g. an invalid URI, resolving the geometry reports the actual error
+	}
+}
+
+template <typename INFO>
+void addCodecInfo(const INFO& info, AbstractCodecInfoBinding* binding) {
+	auto toUTF8 = [](const wchar_t* s) { return (s != nullptr) ? toUTF8FromUTF16(s) : std::string(); };
+	const std::string id = toUTF8(info.getID());
+	const std::string name = toUTF8(info.getName());
+	const std::string description = toUTF8(info.getDescription());
+	const std::string extensions = toUTF8(info.getExtensions());
+	// the codec infos of PRT report a single content type, an undefined one is passed as none
+	std::vector<int32_t> contentTypes;
+	if (info.getType() != prt::CT_UNDEFINED)
+		contentTypes.push_back(static_cast<int32_t>(info.getType()));
+	const CodecInfoWrapper wrapper{id.c_str(),         name.c_str(),        description.c_str(),
+	                               extensions.c_str(), contentTypes.data(), contentTypes.size()};
+	binding->add_codec_info(binding->context, &wrapper);
+}
+
+// every callbacks instance gets its own directory, concurrent generate calls must not overwrite each other's files
+std::filesystem::path createUniqueOutputPath() {
+	static std::atomic<uint64_t> counter{0};
+	const auto basePath = std::filesystem::temp_directory_path() / "prust";
+	std::filesystem::create_directories(basePath);
+	const auto timestamp = std::chrono::system_clock::now().time_since_epoch().count();
+	while (true) {
+		auto path = basePath / (std::to_string(timestamp) + "_" + std::to_string(counter++));
+		if (std::filesystem::create_directory(path)) // false if it exists, e.g. created by another process
+			return path;
+	}
 }
 
 } // namespace
@@ -62,139 +213,682 @@ void InitialShapeWrapper::setAttributes(prt::InitialShapeBuilder& isb, const prt
 	isb.setAttributes(wRuleFile.c_str(), wStartRule.c_str(), randomSeed, wName.c_str(), am, rm);
 }
 
-RustCallbacksBinding::RustCallbacksBinding(AbstractCallbacksBinding* binding) : mBinding(binding) {
-	auto prustTempPath = std::filesystem::temp_directory_path() / "prust";
-	std::filesystem::create_directories(prustTempPath);
-	mDelegate.reset(prt::FileOutputCallbacks::create(prustTempPath.wstring().c_str()));
+void InitialShapeWrapper::setUVsAndTextures(prt::InitialShapeBuilder& isb) const {
+	for (size_t uvSet = 0; uvSet < uvSetsCount; uvSet++) {
+		const UVSetWrapper& uvw = uvSets[uvSet];
+		isb.setUVs(uvw.uvs, uvw.uvsCount, uvw.uvIndices, uvw.uvIndicesCount, uvw.faceRanges, uvw.faceRangesCount,
+		           static_cast<uint32_t>(uvSet));
+	}
+
+	if (texturesCount > 0) {
+		std::vector<std::wstring> wTextures;
+		wTextures.reserve(texturesCount);
+		for (size_t i = 0; i < texturesCount; i++)
+			wTextures.emplace_back(toUTF16FromUTF8(textures[i]));
+		std::vector<const wchar_t*> wTexturePtrs;
+		wTexturePtrs.reserve(texturesCount);
+		for (const auto& t : wTextures)
+			wTexturePtrs.push_back(t.c_str());
+		isb.setTextures(wTexturePtrs.data(), wTexturePtrs.size(), textureFaceRanges, textureFaceRangesCount);
+	}
+}
+
+RustCallbacksBinding::RustCallbacksBinding(AbstractCallbacksBinding* binding, size_t initialShapeCount)
+    : mBinding(binding), mShapeStates(initialShapeCount, 0), mStartTime(std::chrono::steady_clock::now()) {}
+
+prt::FileOutputCallbacks& RustCallbacksBinding::delegate() {
+	std::call_once(mDelegateOnce, [this]() {
+		std::filesystem::path outputPath;
+		if (mBinding->outputDirectory != nullptr) {
+			outputPath = std::filesystem::path(toUTF16FromUTF8(mBinding->outputDirectory));
+			std::filesystem::create_directories(outputPath);
+		}
+		else {
+			outputPath = createUniqueOutputPath();
+			prt::log((L"Writing generated files to " + outputPath.wstring()).c_str(), prt::LOG_INFO);
+		}
+		mDelegate.reset(prt::FileOutputCallbacks::create(outputPath.wstring().c_str()));
+	});
+	return *mDelegate;
+}
+
+bool RustCallbacksBinding::isCanceled() const {
+	// like every call into the Rust callbacks: they are borrowed mutably, the lock serializes the worker threads
+	std::lock_guard<std::mutex> lock(mCallbacksMutex);
+	return mBinding->is_canceled(mBinding->context);
+}
+
+void RustCallbacksBinding::reportProgress(size_t isIndex, ProgressState state, prt::Status status) {
+	const std::chrono::duration<double> elapsed = std::chrono::steady_clock::now() - mStartTime;
+	mBinding->on_progress(mBinding->context, isIndex, mShapeStates.size(), state, status, elapsed.count());
 }
 
-prt::Status RustCallbacksBinding::generateError(size_t /*isIndex*/, prt::Status /*status*/,
-                                                const wchar_t* /*message*/) {
+void RustCallbacksBinding::reportValue(size_t isIndex, const wchar_t* key, const PrimitiveWrapper& value) {
+	const std::string nKey = toUTF8FromUTF16(key);
+	std::lock_guard<std::mutex> lock(mCallbacksMutex);
+	mBinding->on_report(mBinding->context, isIndex, nKey.c_str(), &value);
+}
+
+void RustCallbacksBinding::attributeValue(size_t isIndex, const wchar_t* key, const PrimitiveWrapper& value) {
+	const std::string nKey = toUTF8FromUTF16(key);
+	std::lock_guard<std::mutex> lock(mCallbacksMutex);
+	mBinding->on_attribute(mBinding->context, isIndex, nKey.c_str(), &value);
+}
+
+void RustCallbacksBinding::reportStarted(size_t isIndex) {
+	std::lock_guard<std::mutex> lock(mCallbacksMutex);
+	if (isIndex >= mShapeStates.size() || mShapeStates[isIndex] != 0)
+		return;
+	mShapeStates[isIndex] = 1;
+	reportProgress(isIndex, PROGRESS_STARTED, prt::STATUS_OK);
+}
+
+void RustCallbacksBinding::reportFinished(size_t isIndex, prt::Status status) {
+	std::lock_guard<std::mutex> lock(mCallbacksMutex);
+	if (isIndex >= mShapeStates.size() || mShapeStates[isIndex] == 2)
+		return;
+	if (mShapeStates[isIndex] == 0)
+		reportProgress(isIndex, PROGRESS_STARTED, prt::STATUS_OK);
+	mShapeStates[isIndex] = 2;
+	reportProgress(isIndex, (status == prt::STATUS_OK) ? PROGRESS_FINISHED : PROGRESS_FAILED, status);
+}
+
+void RustCallbacksBinding::finishProgress(prt::Status status) {
+	for (size_t i = 0; i < mShapeStates.size(); i++)
+		reportFinished(i, status);
+}
+
+prt::Status RustCallbacksBinding::addMesh(size_t isIndex, const CapturedMeshWrapper* mesh) {
+	if (isCanceled())
+		return prt::STATUS_CANCELED;
+	reportStarted(isIndex);
+	std::lock_guard<std::mutex> lock(mCallbacksMutex);
+	mBinding->on_mesh(mBinding->context, isIndex, mesh);
+	return prt::STATUS_OK;
+}
+
+prt::Status RustCallbacksBinding::close(uint64_t handle, const size_t* isIndices, size_t isCount) {
+	const prt::Status status = delegate().close(handle, isIndices, isCount);
+	for (size_t i = 0; i < isCount; i++)
+		reportFinished(isIndices[i], status);
+	return status;
+}
+
+prt::Status RustCallbacksBinding::generateError(size_t isIndex, prt::Status status, const wchar_t* /*message*/) {
+	reportFinished(isIndex, status);
+	if (isCanceled())
+		return prt::STATUS_CANCELED;
 	return prt::STATUS_OK;
 }
 
-prt::Status RustCallbacksBinding::assetError(size_t /*isIndex*/, prt::CGAErrorLevel /*level*/, const wchar_t* /*key*/,
+prt::Status RustCallbacksBinding::assetError(size_t isIndex, prt::CGAErrorLevel /*level*/, const wchar_t* /*key*/,
                                              const wchar_t* /*uri*/, const wchar_t* /*message*/) {
+	reportStarted(isIndex);
+	if (isCanceled())
+		return prt::STATUS_CANCELED;
 	return prt::STATUS_OK;
 }
 
-prt::Status RustCallbacksBinding::cgaError(size_t /*isIndex*/, int32_t /*shapeID*/, prt::CGAErrorLevel /*level*/,
+prt::Status RustCallbacksBinding::cgaError(size_t isIndex, int32_t /*shapeID*/, prt::CGAErrorLevel /*level*/,
                                            int32_t /*methodId*/, int32_t /*pc*/, const wchar_t* /*message*/) {
+	reportStarted(isIndex);
+	if (isCanceled())
+		return prt::STATUS_CANCELED;
 	return prt::STATUS_OK;
 }
 
-prt::Status RustCallbacksBinding::cgaPrint(size_t /*isIndex*/, int32_t /*shapeID*/, const wchar_t* /*txt*/) {
+prt::Status RustCallbacksBinding::cgaPrint(size_t isIndex, int32_t /*shapeID*/, const wchar_t* /*txt*/) {
+	reportStarted(isIndex);
+	if (isCanceled())
+		return prt::STATUS_CANCELED;
 	return prt::STATUS_OK;
 }
 
-prt::Status RustCallbacksBinding::cgaReportBool(size_t /*isIndex*/, int32_t /*shapeID*/, const wchar_t* /*key*/,
-                                                bool /*value*/) {
+prt::Status RustCallbacksBinding::cgaReportBool(size_t isIndex, int32_t /*shapeID*/, const wchar_t* key,
+                                                bool value) {
+	reportStarted(isIndex);
+	if (isCanceled())
+		return prt::STATUS_CANCELED;
+	PrimitiveWrapper wrapper{};
+	wrapper.type = prt::AttributeMap::PT_BOOL;
+	wrapper.boolValue = value;
+	reportValue(isIndex, key, wrapper);
 	return prt::STATUS_OK;
 }
 
-prt::Status RustCallbacksBinding::cgaReportFloat(size_t /*isIndex*/, int32_t /*shapeID*/, const wchar_t* /*key*/,
-                                                 double /*value*/) {
+prt::Status RustCallbacksBinding::cgaReportFloat(size_t isIndex, int32_t /*shapeID*/, const wchar_t* key,
+                                                 double value) {
+	reportStarted(isIndex);
+	if (isCanceled())
+		return prt::STATUS_CANCELED;
+	PrimitiveWrapper wrapper{};
+	wrapper.type = prt::AttributeMap::PT_FLOAT;
+	wrapper.floatValue = value;
+	reportValue(isIndex, key, wrapper);
 	return prt::STATUS_OK;
 }
 
-prt::Status RustCallbacksBinding::cgaReportString(size_t /*isIndex*/, int32_t /*shapeID*/, const wchar_t* /*key*/,
-                                                  const wchar_t* /*value*/) {
+prt::Status RustCallbacksBinding::cgaReportString(size_t isIndex, int32_t /*shapeID*/, const wchar_t* key,
+                                                  const wchar_t* value) {
+	reportStarted(isIndex);
+	if (isCanceled())
+		return prt::STATUS_CANCELED;
+	const std::string nValue = toUTF8FromUTF16(value);
+	PrimitiveWrapper wrapper{};
+	wrapper.type = prt::AttributeMap::PT_STRING;
+	wrapper.stringValue = nValue.c_str();
+	reportValue(isIndex, key, wrapper);
 	return prt::STATUS_OK;
 }
 
-prt::Status RustCallbacksBinding::attrBool(size_t /*isIndex*/, int32_t /*shapeID*/, const wchar_t* /*key*/,
-                                           bool /*value*/) {
+prt::Status RustCallbacksBinding::attrBool(size_t isIndex, int32_t /*shapeID*/, const wchar_t* key, bool value) {
+	reportStarted(isIndex);
+	if (isCanceled())
+		return prt::STATUS_CANCELED;
+	PrimitiveWrapper wrapper{};
+	wrapper.type = prt::AttributeMap::PT_BOOL;
+	wrapper.boolValue = value;
+	attributeValue(isIndex, key, wrapper);
 	return prt::STATUS_OK;
 }
 
-prt::Status RustCallbacksBinding::attrFloat(size_t /*isIndex*/, int32_t /*shapeID*/, const wchar_t* /*key*/,
-                                            double /*value*/) {
+prt::Status RustCallbacksBinding::attrFloat(size_t isIndex, int32_t /*shapeID*/, const wchar_t* key, double value) {
+	reportStarted(isIndex);
+	if (isCanceled())
+		return prt::STATUS_CANCELED;
+	PrimitiveWrapper wrapper{};
+	wrapper.type = prt::AttributeMap::PT_FLOAT;
+	wrapper.floatValue = value;
+	attributeValue(isIndex, key, wrapper);
 	return prt::STATUS_OK;
 }
 
-prt::Status RustCallbacksBinding::attrString(size_t /*isIndex*/, int32_t /*shapeID*/, const wchar_t* /*key*/,
-                                             const wchar_t* /*value*/) {
+prt::Status RustCallbacksBinding::attrString(size_t isIndex, int32_t /*shapeID*/, const wchar_t* key,
+                                             const wchar_t* value) {
+	reportStarted(isIndex);
+	if (isCanceled())
+		return prt::STATUS_CANCELED;
+	const std::string nValue = toUTF8FromUTF16(value);
+	PrimitiveWrapper wrapper{};
+	wrapper.type = prt::AttributeMap::PT_STRING;
+	wrapper.stringValue = nValue.c_str();
+	attributeValue(isIndex, key, wrapper);
 	return prt::STATUS_OK;
 }
 
-prt::Status RustCallbacksBinding::attrBoolArray(size_t /*isIndex*/, int32_t /*shapeID*/, const wchar_t* /*key*/,
-                                                const bool* /*values*/, size_t /*size*/, size_t /*nRows*/) {
+prt::Status RustCallbacksBinding::attrBoolArray(size_t isIndex, int32_t /*shapeID*/, const wchar_t* key,
+                                                const bool* values, size_t size, size_t nRows) {
+	reportStarted(isIndex);
+	if (isCanceled())
+		return prt::STATUS_CANCELED;
+	PrimitiveWrapper wrapper{};
+	wrapper.type = prt::AttributeMap::PT_BOOL_ARRAY;
+	wrapper.boolArray = values;
+	wrapper.count = size;
+	wrapper.rows = nRows;
+	attributeValue(isIndex, key, wrapper);
 	return prt::STATUS_OK;
 }
 
-prt::Status RustCallbacksBinding::attrFloatArray(size_t /*isIndex*/, int32_t /*shapeID*/, const wchar_t* /*key*/,
-                                                 const double* /*values*/, size_t /*size*/, size_t /*nRows*/) {
+prt::Status RustCallbacksBinding::attrFloatArray(size_t isIndex, int32_t /*shapeID*/, const wchar_t* key,
+                                                 const double* values, size_t size, size_t nRows) {
+	reportStarted(isIndex);
+	if (isCanceled())
+		return prt::STATUS_CANCELED;
+	PrimitiveWrapper wrapper{};
+	wrapper.type = prt::AttributeMap::PT_FLOAT_ARRAY;
+	wrapper.floatArray = values;
+	wrapper.count = size;
+	wrapper.rows = nRows;
+	attributeValue(isIndex, key, wrapper);
 	return prt::STATUS_OK;
 }
 
-prt::Status RustCallbacksBinding::attrStringArray(size_t /*isIndex*/, int32_t /*shapeID*/, const wchar_t* /*key*/,
-                                                  const wchar_t* const* /*values*/, size_t /*size*/, size_t /*nRows*/) {
+prt::Status RustCallbacksBinding::attrStringArray(size_t isIndex, int32_t /*shapeID*/, const wchar_t* key,
+                                                  const wchar_t* const* values, size_t size, size_t nRows) {
+	reportStarted(isIndex);
+	if (isCanceled())
+		return prt::STATUS_CANCELED;
+	std::vector<std::string> nValues;
+	std::vector<const char*> nValuePtrs;
+	nValues.reserve(size);
+	for (size_t i = 0; i < size; i++)
+		nValues.emplace_back(toUTF8FromUTF16(values[i]));
+	for (const auto& v : nValues)
+		nValuePtrs.push_back(v.c_str());
+	PrimitiveWrapper wrapper{};
+	wrapper.type = prt::AttributeMap::PT_STRING_ARRAY;
+	wrapper.stringArray = nValuePtrs.data();
+	wrapper.count = size;
+	wrapper.rows = nRows;
+	attributeValue(isIndex, key, wrapper);
 	return prt::STATUS_OK;
 }
 
+namespace {
+
+// converts the initial shape wrappers into PRT initial shapes and keeps them (and their dependencies) alive
+class InitialShapes {
+public:
+	InitialShapes(const InitialShapeWrapper* const* ffiInitialShapes, size_t initialShapeCount) {
+		mInitialShapes.reserve(initialShapeCount);
+		mInitialShapeAttributes.reserve(initialShapeCount);
+		mResolveMaps.reserve(initialShapeCount);
+		mInitialShapePtrs.resize(initialShapeCount, nullptr);
+
+		InitialShapeBuilderUPtr isb(prt::InitialShapeBuilder::create());
+		AttributeMapBuilderUPtr amb(prt::AttributeMapBuilder::create());
+		ResolveMapBuilderUPtr rmb(prt::ResolveMapBuilder::create());
+		for (size_t i = 0; i < initialShapeCount; i++) {
+			const InitialShapeWrapper& isw = *ffiInitialShapes[i];
+			isb->setGeometry(isw.vertexCoords, isw.vertexCoordsCount, isw.indices, isw.indicesCount, isw.faceCounts,
+			                 isw.faceCountsCount, isw.holes, isw.holesCount);
+			isw.setUVsAndTextures(*isb);
+			mResolveMaps.emplace_back(rmb->createResolveMapAndReset()); // TODO
+
+			// PRT needs an attribute map even if no attributes are set
+			const prt::AttributeMap* attributes = isw.attributes;
+			if (attributes == nullptr) {
+				mInitialShapeAttributes.emplace_back(amb->createAttributeMapAndReset());
+				attributes = mInitialShapeAttributes.back().get();
+			}
+
+			isw.setAttributes(*isb, attributes, mResolveMaps.back().get());
+
+			mInitialShapes.emplace_back(isb->createInitialShapeAndReset());
+			mInitialShapePtrs[i] = mInitialShapes.back().get();
+		}
+	}
+
+	const prt::InitialShape* const* data() const {
+		return mInitialShapePtrs.data();
+	}
+
+	size_t size() const {
+		return mInitialShapePtrs.size();
+	}
+
+private:
+	std::vector<InitialShapeUPtr> mInitialShapes;
+	std::vector<AttributeMapUPtr> mInitialShapeAttributes;
+	std::vector<ResolveMapUPtr> mResolveMaps;
+	InitialShapeNOPtrVector mInitialShapePtrs;
+};
+
+// one validated option map per encoder
+// the geometry capture encoder gets the address of the callbacks as option
+const prt::AttributeMap* createCaptureOptions(const prt::AttributeMap* options,
+                                              const GeometryCaptureCallbacks* captureCallbacks) {
+	AttributeMapBuilderUPtr amb((options != nullptr) ? prt::AttributeMapBuilder::createFromAttributeMap(options)
+	                                                 : prt::AttributeMapBuilder::create());
+	std::wostringstream address;
+	address << std::hex << reinterpret_cast<uintptr_t>(captureCallbacks);
+	amb->setString(EO_CAPTURE_CALLBACKS, address.str().c_str());
+	return amb->createAttributeMap();
+}
+
+class EncoderOptions {
+public:
+	EncoderOptions(const wchar_t* const* encoders, size_t encodersCount,
+	               const prt::AttributeMap* const* unvalidatedOptions,
+	               const GeometryCaptureCallbacks* captureCallbacks) {
+		for (size_t i = 0; i < encodersCount; i++) {
+			const prt::AttributeMap* unvalidated = (unvalidatedOptions != nullptr) ? unvalidatedOptions[i] : nullptr;
+			AttributeMapUPtr captureOptions;
+			if (std::wcscmp(encoders[i], GEOMETRY_CAPTURE_ENCODER_ID) == 0) {
+				captureOptions.reset(createCaptureOptions(unvalidated, captureCallbacks));
+				unvalidated = captureOptions.get();
+			}
+			mOwners.emplace_back(createValidatedOptions(encoders[i], unvalidated));
+			mOptionPtrs.push_back(mOwners.back().get());
+		}
+	}
+
+	const prt::AttributeMap* const* data() const {
+		return mOptionPtrs.data();
+	}
+
+private:
+	std::vector<AttributeMapUPtr> mOwners;
+	std::vector<const prt::AttributeMap*> mOptionPtrs;
+};
+
+} // namespace
+
 prt::Status ffi_generate(const InitialShapeWrapper* const* ffiInitialShapes, size_t initialShapeCount,
                          const prt::OcclusionSet::Handle* occlusionHandles, const wchar_t* const* encoders,
-                         size_t encodersCount, const prt::AttributeMap* const* /*encoderOptions*/,
+                         size_t encodersCount, const prt::AttributeMap* const* unvalidatedEncoderOptions,
                          AbstractCallbacksBinding* callbacks, prt::Cache* cache, const prt::OcclusionSet* occlSet,
                          const prt::AttributeMap* generateOptions) {
-	std::vector<InitialShapeUPtr> initialShapes; // keeps the initial shapes alive
-	initialShapes.reserve(initialShapeCount);
-	std::vector<AttributeMapUPtr> initialShapeAttributes; // keeps the initial shape attrs alive
-	initialShapeAttributes.reserve(initialShapeCount);
-	std::vector<ResolveMapUPtr> resolveMaps; // keeps the initial shape resolve maps alive
-	resolveMaps.reserve(initialShapeCount);
-
-	InitialShapeNOPtrVector initialShapePtrs(initialShapeCount, nullptr);
+	const InitialShapes initialShapes(ffiInitialShapes, initialShapeCount);
+	auto callbacksBinding = std::make_unique<RustCallbacksBinding>(callbacks, initialShapes.size());
+	const EncoderOptions encoderOptions(encoders, encodersCount, unvalidatedEncoderOptions, callbacksBinding.get());
+	prt::Status status =
+	        prt::generate(initialShapes.data(), initialShapes.size(), occlusionHandles, encoders, encodersCount,
+	                      encoderOptions.data(), callbacksBinding.get(), cache, occlSet, generateOptions);
+	if (status != prt::STATUS_OK && callbacksBinding->isCanceled())
+		status = prt::STATUS_CANCELED;
+	callbacksBinding->finishProgress(status);
+	return status;
+}
+
+prt::Status ffi_generate_occluders(const InitialShapeWrapper* const* ffiInitialShapes, size_t initialShapeCount,
+                                   prt::OcclusionSet::Handle* occlusionHandles, const wchar_t* const* encoders,
+                                   size_t encodersCount, const prt::AttributeMap* const* unvalidatedEncoderOptions,
+                                   AbstractCallbacksBinding* callbacks, prt::Cache* cache, prt::OcclusionSet* occlSet,
+                                   const prt::AttributeMap* generateOptions) {
+	const InitialShapes initialShapes(ffiInitialShapes, initialShapeCount);
+	auto callbacksBinding = std::make_unique<RustCallbacksBinding>(callbacks, initialShapes.size());
+	const EncoderOptions encoderOptions(encoders, encodersCount, unvalidatedEncoderOptions, callbacksBinding.get());
+	prt::Status status = prt::generateOccluders(initialShapes.data(), initialShapes.size(), occlusionHandles, encoders,
+	                                            encodersCount, encoderOptions.data(), callbacksBinding.get(), cache,
+	                                            occlSet, generateOptions);
+	if (status != prt::STATUS_OK && callbacksBinding->isCanceled())
+		status = prt::STATUS_CANCELED;
+	callbacksBinding->finishProgress(status);
+	return status;
+}
+
+prt::OcclusionSet* ffi_occlusion_set_create() {
+	return prt::OcclusionSet::create();
+}
+
+void ffi_occlusion_set_destroy(prt::OcclusionSet* occlSet) {
+	if (occlSet != nullptr)
+		occlSet->destroy();
+}
+
+const prt::ResolveMap* ffi_create_resolve_map(const char* uri, prt::Status* status) {
+	return prt::createResolveMap(toUTF16FromUTF8(uri).c_str(), nullptr, status);
+}
+
+void ffi_resolve_map_destroy(const prt::ResolveMap* resolveMap) {
+	if (resolveMap != nullptr)
+		resolveMap->destroy();
+}
+
+prt::Cache* ffi_cache_create() {
+	return prt::CacheObject::create(prt::CacheObject::CACHE_TYPE_DEFAULT);
+}
+
+void ffi_cache_destroy(prt::Cache* cache) {
+	if (cache != nullptr)
+		cache->destroy();
+}
+
+prt::Status ffi_read_binary_data(const char* keyOrUri, const prt::ResolveMap* resolveMap, prt::Cache* cache,
+                                 AbstractBinaryDataBinding* dataBinding) {
+	std::unique_ptr<prt::Cache, PRTObjectDestroyer> tmpCache;
+	if (cache == nullptr) {
+		tmpCache.reset(prt::CacheObject::create(prt::CacheObject::CACHE_TYPE_DEFAULT));
+		cache = tmpCache.get();
+	}
+
+	try {
+		// keys are resolved with the resolve map, anything else is treated as URI
+		const prtx::BinaryVectorPtr data =
+		        prtx::DataBackend::resolveBinaryData(cache, toUTF16FromUTF8(keyOrUri), resolveMap);
+		if (!data)
+			return prt::STATUS_FILE_NOT_FOUND;
+		dataBinding->set_data(dataBinding->context, data->data(), data->size());
+		return prt::STATUS_OK;
+	}
+	catch (const prtx::StatusException& e) {
+		return e.getStatus();
+	}
+	catch (const std::exception& e) {
+		const std::wstring msg = L"Error while reading " + toUTF16FromUTF8(keyOrUri) + L": " + toUTF16FromUTF8(e.what());
+		prt::log(msg.c_str(), prt::LOG_ERROR);
+		return prt::STATUS_UNSPECIFIED_ERROR;
+	}
+	catch (...) {
+		prt::log((L"Unknown error while reading " + toUTF16FromUTF8(keyOrUri)).c_str(), prt::LOG_ERROR);
+		return prt::STATUS_UNSPECIFIED_ERROR;
+	}
+}
+
+prt::Status ffi_resolve_geometry(const char* uri, const prt::ResolveMap* resolveMap, prt::Cache* cache,
+                                 AbstractGeometryBinding* geometryBinding) {
+	const std::wstring wUri = toUTF16FromUTF8(uri);
+	const std::string decoderId = toUTF8FromUTF16(findDecoderId(wUri, resolveMap));
+	const char* decoderIdPtr = decoderId.empty() ? nullptr : decoderId.c_str();
+
 	InitialShapeBuilderUPtr isb(prt::InitialShapeBuilder::create());
+	prt::Status status = isb->resolveGeometry(wUri.c_str(), resolveMap, cache);
+	if (status != prt::STATUS_OK) {
+		geometryBinding->set_geometry(geometryBinding->context, nullptr, 0, nullptr, 0, nullptr, 0, nullptr, 0,
+		                              decoderIdPtr);
+		return status;
+	}
+
+	// we only need the geometry, the rule attributes are placeholders to be able to create the initial shape
 	AttributeMapBuilderUPtr amb(prt::AttributeMapBuilder::create());
-	ResolveMapBuilderUPtr rmb(prt::ResolveMapBuilder::create());
-	for (size_t i = 0; i < initialShapeCount; i++) {
-		const InitialShapeWrapper& isw = *ffiInitialShapes[i];
-		isb->setGeometry(isw.vertexCoords, isw.vertexCoordsCount, isw.indices, isw.indicesCount, isw.faceCounts,
-		                 isw.faceCountsCount);
-		initialShapeAttributes.emplace_back(amb->createAttributeMapAndReset()); // TODO
-		resolveMaps.emplace_back(rmb->createResolveMapAndReset());              // TODO
+	const AttributeMapUPtr attributes(amb->createAttributeMap());
+	isb->setAttributes(L"prust/geometry.cgb", L"Default$Init", 0, L"", attributes.get(), resolveMap);
+	const InitialShapeUPtr initialShape(isb->createInitialShape(&status));
+	if (!initialShape || status != prt::STATUS_OK)
+		return (status != prt::STATUS_OK) ? status : prt::STATUS_UNSPECIFIED_ERROR;
+
+	geometryBinding->set_geometry(geometryBinding->context, initialShape->getVertices(),
+	                              initialShape->getVertexCount(), initialShape->getIndices(),
+	                              initialShape->getIndexCount(), initialShape->getFaceCounts(),
+	                              initialShape->getFaceCountsCount(), initialShape->getHoles(),
+	                              initialShape->getHolesCount(), decoderIdPtr);
+	return prt::STATUS_OK;
+}
 
-		isw.setAttributes(*isb, initialShapeAttributes.back().get(), resolveMaps.back().get());
+prt::Status ffi_get_codec_infos(CodecKind kind, const char* codecId, AbstractCodecInfoBinding* codecInfoBinding) {
+	std::vector<std::wstring> codecIds;
+	if (codecId != nullptr)
+		codecIds.emplace_back(toUTF16FromUTF8(codecId));
+	else if (kind == CODEC_ENCODER)
+		codecIds = listExtensionIds(prt::listEncoderIds);
+	else
+		codecIds = listExtensionIds(prt::listDecoderIds);
+
+	for (const std::wstring& id : codecIds) {
+		prt::Status status = prt::STATUS_OK;
+		if (kind == CODEC_ENCODER) {
+			const EncoderInfoUPtr encInfo(prt::createEncoderInfo(id.c_str(), &status));
+			if (encInfo)
+				addCodecInfo(*encInfo, codecInfoBinding);
+		}
+		else {
+			const DecoderInfoUPtr decInfo(prt::createDecoderInfo(id.c_str(), &status));
+			if (decInfo)
+				addCodecInfo(*decInfo, codecInfoBinding);
+		}
+		if (status != prt::STATUS_OK && codecId != nullptr)
+			return status;
+	}
+	return prt::STATUS_OK;
+}
 
-		initialShapes.emplace_back(isb->createInitialShapeAndReset());
-		initialShapePtrs[i] = initialShapes.back().get();
+prt::Status ffi_validate_encoder_options(const char* encoderId, const prt::AttributeMap* unvalidatedOptions,
+                                         AbstractAttributeMapBinding* validatedOptions,
+                                         AbstractAttributeMapBinding* optionStates) {
+	AttributeMapUPtr validated;
+	AttributeMapUPtr states;
+	const prt::Status status = createValidatedOptionsAndStates(toUTF16FromUTF8(encoderId).c_str(), unvalidatedOptions,
+	                                                           validated, states);
+	if (status != prt::STATUS_OK)
+		return status;
+	visitAttributeMap(validated.get(), validatedOptions);
+	visitAttributeMap(states.get(), optionStates);
+	return prt::STATUS_OK;
+}
+
+prt::Status ffi_get_encoder_option_annotations(const char* encoderId, AbstractOptionAnnotationBinding* binding) {
+	prt::Status status = prt::STATUS_OK;
+	const EncoderInfoUPtr encInfo(prt::createEncoderInfo(toUTF16FromUTF8(encoderId).c_str(), &status));
+	if (!encInfo)
+		return (status != prt::STATUS_OK) ? status : prt::STATUS_ENCODER_NOT_FOUND;
+
+	// the option keys are the ones of the default options
+	const prt::AttributeMap* defaults = nullptr;
+	const prt::AttributeMap* states = nullptr;
+	status = encInfo->createValidatedOptionsAndStates(nullptr, &defaults, &states);
+	const AttributeMapUPtr defaultsOwner(defaults);
+	const AttributeMapUPtr statesOwner(states);
+	if (status != prt::STATUS_OK)
+		return status;
+
+	size_t keyCount = 0;
+	const wchar_t* const* keys = defaults->getKeys(&keyCount);
+	for (size_t k = 0; k < keyCount; k++) {
+		const std::string nKey = toUTF8FromUTF16(keys[k]);
+		size_t annotationCount = 0;
+		const prt::Annotation* const* annotations = encInfo->getAnnotations(keys[k], &annotationCount);
+		for (size_t a = 0; a < annotationCount; a++) {
+			const prt::Annotation* annotation = annotations[a];
+			const std::string name = toUTF8FromUTF16(annotation->getName());
+
+			// owners of the converted strings
+			const size_t argumentCount = annotation->getNumArguments();
+			std::vector<std::string> argumentKeys(argumentCount);
+			std::vector<std::string> stringValues(argumentCount);
+			std::vector<PrimitiveWrapper> arguments(argumentCount);
+			for (size_t i = 0; i < argumentCount; i++) {
+				const prt::AnnotationArgument* argument = annotation->getArgument(i);
+				if (argument->getKey() != nullptr)
+					argumentKeys[i] = toUTF8FromUTF16(argument->getKey());
+				PrimitiveWrapper& value = arguments[i];
+				switch (argument->getType()) {
+					case prt::AAT_BOOL:
+						value.type = prt::AttributeMap::PT_BOOL;
+						value.boolValue = argument->getBool();
+						break;
+					case prt::AAT_FLOAT:
+						value.type = prt::AttributeMap::PT_FLOAT;
+						value.floatValue = argument->getFloat();
+						break;
+					case prt::AAT_INT:
+						value.type = prt::AttributeMap::PT_INT;
+						value.intValue = argument->getInt();
+						break;
+					case prt::AAT_STR:
+						value.type = prt::AttributeMap::PT_STRING;
+						stringValues[i] = toUTF8FromUTF16(argument->getStr());
+						value.stringValue = stringValues[i].c_str();
+						break;
+					default:
+						value.type = prt::AttributeMap::PT_UNDEFINED;
+						break;
+				}
+			}
+			std::vector<const char*> argumentKeyPtrs;
+			for (const auto& argumentKey : argumentKeys)
+				argumentKeyPtrs.push_back(argumentKey.c_str());
+			binding->add_annotation(binding->context, nKey.c_str(), name.c_str(), argumentKeyPtrs.data(),
+			                        arguments.data(), arguments.size());
+		}
 	}
+	return prt::STATUS_OK;
+}
 
-	AttributeMapUPtr validatedEncOpts(createValidatedOptions(encoders[0]));
-	std::vector<const prt::AttributeMap*> tmpEncoderOpts{validatedEncOpts.get()};
+prt::AttributeMapBuilder* ffi_attribute_map_builder_create() {
+	return prt::AttributeMapBuilder::create();
+}
 
-	auto callbacksBinding = std::make_unique<RustCallbacksBinding>(callbacks);
-	prt::Status status =
-	        prt::generate(initialShapePtrs.data(), initialShapePtrs.size(), occlusionHandles, encoders, encodersCount,
-	                      tmpEncoderOpts.data(), callbacksBinding.get(), cache, occlSet, generateOptions);
-	return status;
+void ffi_attribute_map_builder_destroy(prt::AttributeMapBuilder* amb) {
+	if (amb != nullptr)
+		amb->destroy();
+}
+
+void ffi_attribute_map_builder_set_bool(prt::AttributeMapBuilder* amb, const char* key, bool value) {
+	amb->setBool(toUTF16FromUTF8(key).c_str(), value);
+}
+
+void ffi_attribute_map_builder_set_float(prt::AttributeMapBuilder* amb, const char* key, double value) {
+	amb->setFloat(toUTF16FromUTF8(key).c_str(), value);
+}
+
+void ffi_attribute_map_builder_set_int(prt::AttributeMapBuilder* amb, const char* key, int32_t value) {
+	amb->setInt(toUTF16FromUTF8(key).c_str(), value);
+}
+
+void ffi_attribute_map_builder_set_string(prt::AttributeMapBuilder* amb, const char* key, const char* value) {
+	amb->setString(toUTF16FromUTF8(key).c_str(), toUTF16FromUTF8(value).c_str());
+}
+
+void ffi_attribute_map_builder_set_bool_array(prt::AttributeMapBuilder* amb, const char* key, const bool* values,
+                                              size_t count, size_t rows) {
+	amb->setBoolArray(toUTF16FromUTF8(key).c_str(), values, count, rows);
+}
+
+void ffi_attribute_map_builder_set_float_array(prt::AttributeMapBuilder* amb, const char* key, const double* values,
+                                               size_t count, size_t rows) {
+	amb->setFloatArray(toUTF16FromUTF8(key).c_str(), values, count, rows);
+}
+
+void ffi_attribute_map_builder_set_int_array(prt::AttributeMapBuilder* amb, const char* key, const int32_t* values,
+                                             size_t count, size_t rows) {
+	amb->setIntArray(toUTF16FromUTF8(key).c_str(), values, count, rows);
+}
+
+void ffi_attribute_map_builder_set_string_array(prt::AttributeMapBuilder* amb, const char* key,
+                                                const char* const* values, size_t count, size_t rows) {
+	std::vector<std::wstring> wValues;
+	wValues.reserve(count);
+	for (size_t i = 0; i < count; i++)
+		wValues.emplace_back(toUTF16FromUTF8(values[i]));
+	std::vector<const wchar_t*> wValuePtrs;
+	wValuePtrs.reserve(count);
+	for (const auto& v : wValues)
+		wValuePtrs.push_back(v.c_str());
+	amb->setStringArray(toUTF16FromUTF8(key).c_str(), wValuePtrs.data(), wValuePtrs.size(), rows);
+}
+
+const prt::AttributeMap* ffi_attribute_map_builder_create_attribute_map(prt::AttributeMapBuilder* amb) {
+	return amb->createAttributeMap();
+}
+
+void ffi_attribute_map_destroy(const prt::AttributeMap* am) {
+	if (am != nullptr)
+		am->destroy();
 }
 
 namespace {
 
 using HandlerHolder = std::map<void*, std::unique_ptr<RustLogHandlerBinding>>;
 HandlerHolder logHandlerHolder;
+// removed handlers are detached but kept alive, PRT may still be about to call them from a worker thread
+std::vector<std::unique_ptr<RustLogHandlerBinding>> removedLogHandlers;
+std::mutex logHandlerHolderMutex; // log handlers may be added and removed from any thread
 
 } // namespace
 
-void ffi_add_log_handler(AbstractLogHandlerBinding* logHandler) {
-	auto [handlerIt, done] =
+bool ffi_add_log_handler(AbstractLogHandlerBinding* logHandler) {
+	std::lock_guard<std::mutex> lock(logHandlerHolderMutex);
+	auto [handlerIt, added] =
 	        logHandlerHolder.emplace(logHandler->context, std::make_unique<RustLogHandlerBinding>(logHandler));
-	prt::addLogHandler(handlerIt->second.get());
+	if (added)
+		prt::addLogHandler(handlerIt->second.get());
+	return added;
 };
 
-void ffi_remove_log_handler(AbstractLogHandlerBinding* logHandler) {
-	auto it = logHandlerHolder.find(logHandler->context);
-	if (it != logHandlerHolder.end()) {
-		prt::removeLogHandler(it->second.get());
-	}
-	delete logHandler;
+AbstractLogHandlerBinding* ffi_remove_log_handler(void* context) {
+	std::lock_guard<std::mutex> lock(logHandlerHolderMutex);
+	auto it = logHandlerHolder.find(context);
+	if (it == logHandlerHolder.end())
+		return nullptr;
+	prt::removeLogHandler(it->second.get());
+	AbstractLogHandlerBinding* binding = it->second->detach();
+	removedLogHandlers.push_back(std::move(it->second));
+	logHandlerHolder.erase(it); // allows to add a handler at the same address again
+	return binding;
 }
 
 void RustLogHandlerBinding::handleLogEvent(const wchar_t* msg, prt::LogLevel /*level*/) {
 	std::string nMsg = toUTF8FromUTF16(msg);
-	(*mBinding->handle_log_event)(mBinding->context, nMsg.c_str());
+	std::lock_guard<std::mutex> lock(mMutex);
+	if (mBinding != nullptr)
+		(*mBinding->handle_log_event)(mBinding->context, nMsg.c_str());
+}
+
+AbstractLogHandlerBinding* RustLogHandlerBinding::detach() {
+	std::lock_guard<std::mutex> lock(mMutex);
+	return std::exchange(mBinding, nullptr);
 }
 
 const prt::LogLevel* RustLogHandlerBinding::getLevels(size_t* count) {
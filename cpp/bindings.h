@@ -1,5 +1,6 @@
 #pragma once
 
+#include "prt/AttributeMap.h"
 #include "prt/Cache.h"
 #include "prt/Callbacks.h"
 #include "prt/ContentType.h"
@@ -8,10 +9,16 @@
 #include "prt/LogHandler.h"
 #include "prt/LogLevel.h"
 #include "prt/OcclusionSet.h"
+#include "prt/ResolveMap.h"
 #include "prt/Status.h"
 #include "prt/StringUtils.h"
 
+#include "GeometryCaptureCallbacks.h"
+
+#include <chrono>
 #include <memory>
+#include <mutex>
+#include <vector>
 
 struct PRTObjectDestroyer {
 	void operator()(prt::Object const* p) {
@@ -22,12 +29,17 @@ struct PRTObjectDestroyer {
 
 extern "C" {
 
-struct AttributeMapWrapper {
+struct ResolveMapWrapper {
 	int32_t dummy;
 };
 
-struct ResolveMapWrapper {
-	int32_t dummy;
+struct UVSetWrapper {
+	double const* uvs;
+	size_t uvsCount;
+	uint32_t const* uvIndices;
+	size_t uvIndicesCount;
+	uint32_t const* faceRanges;
+	size_t faceRangesCount;
 };
 
 struct InitialShapeWrapper {
@@ -37,19 +49,51 @@ struct InitialShapeWrapper {
 	size_t indicesCount;
 	uint32_t const* faceCounts;
 	size_t faceCountsCount;
+	uint32_t const* holes; // face index, hole face indices, HOLE_DELIM, ...
+	size_t holesCount;
+	UVSetWrapper const* uvSets; // the index in this array is the uv set
+	size_t uvSetsCount;
+	const char* const* textures; // keys or URIs
+	size_t texturesCount;
+	uint32_t const* textureFaceRanges;
+	size_t textureFaceRangesCount;
 
 	const char* ruleFile;
 	const char* startRule;
 	int32_t randomSeed;
 	const char* name;
-	const AttributeMapWrapper* attributes;
+	const prt::AttributeMap* attributes; // owned by the Rust side, may be null
 	const ResolveMapWrapper* resolveMap;
 
 	void setAttributes(prt::InitialShapeBuilder& isb, const prt::AttributeMap* am, const prt::ResolveMap* rm) const;
+	void setUVsAndTextures(prt::InitialShapeBuilder& isb) const;
+};
+
+struct PrimitiveWrapper {
+	int32_t type; // prt::AttributeMap::PrimitiveType
+	bool boolValue;
+	double floatValue;
+	int32_t intValue;
+	const char* stringValue;
+	const bool* boolArray;
+	const double* floatArray;
+	const int32_t* intArray;
+	const char* const* stringArray;
+	size_t count;
+	size_t rows; // as reported by PRT, 1 for plain arrays and 0 for non-arrays
 };
 
+enum ProgressState : int32_t { PROGRESS_STARTED, PROGRESS_FINISHED, PROGRESS_FAILED };
+
 struct AbstractCallbacksBinding {
-	void* context; // the actual Rust implementation
+	void* context;               // the actual Rust implementation
+	const char* outputDirectory; // UTF-8, a new temporary directory is used if null
+	bool (*is_canceled)(void* ctx);
+	void (*on_progress)(void* ctx, size_t isIndex, size_t isCount, ProgressState state, prt::Status status,
+	                    double elapsedSeconds);
+	void (*on_mesh)(void* ctx, size_t isIndex, const CapturedMeshWrapper* mesh);
+	void (*on_report)(void* ctx, size_t isIndex, const char* key, const PrimitiveWrapper* value);
+	void (*on_attribute)(void* ctx, size_t isIndex, const char* key, const PrimitiveWrapper* value);
 };
 
 prt::Status ffi_generate(const InitialShapeWrapper* const* initialShapes, size_t initialShapeCount,
@@ -58,11 +102,104 @@ prt::Status ffi_generate(const InitialShapeWrapper* const* initialShapes, size_t
                          AbstractCallbacksBinding* callbacks, prt::Cache* cache, const prt::OcclusionSet* occlSet,
                          const prt::AttributeMap* generateOptions);
 
+prt::Status ffi_generate_occluders(const InitialShapeWrapper* const* initialShapes, size_t initialShapeCount,
+                                   prt::OcclusionSet::Handle* occlusionHandles, const wchar_t* const* encoders,
+                                   size_t encodersCount, const prt::AttributeMap* const* encoderOptions,
+                                   AbstractCallbacksBinding* callbacks, prt::Cache* cache, prt::OcclusionSet* occlSet,
+                                   const prt::AttributeMap* generateOptions);
+
+prt::OcclusionSet* ffi_occlusion_set_create();
+void ffi_occlusion_set_destroy(prt::OcclusionSet* occlSet);
+
+// keys and string values are UTF-8 encoded
+prt::AttributeMapBuilder* ffi_attribute_map_builder_create();
+void ffi_attribute_map_builder_destroy(prt::AttributeMapBuilder* amb);
+void ffi_attribute_map_builder_set_bool(prt::AttributeMapBuilder* amb, const char* key, bool value);
+void ffi_attribute_map_builder_set_float(prt::AttributeMapBuilder* amb, const char* key, double value);
+void ffi_attribute_map_builder_set_int(prt::AttributeMapBuilder* amb, const char* key, int32_t value);
+void ffi_attribute_map_builder_set_string(prt::AttributeMapBuilder* amb, const char* key, const char* value);
+// arrays with rows > 1 are CGA 2D arrays, the values are passed row by row
+void ffi_attribute_map_builder_set_bool_array(prt::AttributeMapBuilder* amb, const char* key, const bool* values,
+                                              size_t count, size_t rows);
+void ffi_attribute_map_builder_set_float_array(prt::AttributeMapBuilder* amb, const char* key, const double* values,
+                                               size_t count, size_t rows);
+void ffi_attribute_map_builder_set_int_array(prt::AttributeMapBuilder* amb, const char* key, const int32_t* values,
+                                             size_t count, size_t rows);
+void ffi_attribute_map_builder_set_string_array(prt::AttributeMapBuilder* amb, const char* key,
+                                                const char* const* values, size_t count, size_t rows);
+const prt::AttributeMap* ffi_attribute_map_builder_create_attribute_map(prt::AttributeMapBuilder* amb);
+void ffi_attribute_map_destroy(const prt::AttributeMap* am);
+
+struct AbstractAttributeMapBinding {
+	void (*add_attribute)(void* ctx, const char* key, const PrimitiveWrapper* value);
+	void* context; // the actual Rust implementation
+};
+
+// passes the validated options and their states to the bindings
+prt::Status ffi_validate_encoder_options(const char* encoderId, const prt::AttributeMap* unvalidatedOptions,
+                                         AbstractAttributeMapBinding* validatedOptions,
+                                         AbstractAttributeMapBinding* optionStates);
+
+struct AbstractOptionAnnotationBinding {
+	// called per annotation of an option, e.g. @Range(min=0, max=1) with the arguments 0 and 1
+	void (*add_annotation)(void* ctx, const char* optionKey, const char* name, const char* const* argumentKeys,
+	                       const PrimitiveWrapper* arguments, size_t argumentsCount);
+	void* context; // the actual Rust implementation
+};
+
+// passes the annotations of all encoder options to the binding
+prt::Status ffi_get_encoder_option_annotations(const char* encoderId, AbstractOptionAnnotationBinding* binding);
+
+const prt::ResolveMap* ffi_create_resolve_map(const char* uri, prt::Status* status);
+void ffi_resolve_map_destroy(const prt::ResolveMap* resolveMap);
+
+prt::Cache* ffi_cache_create();
+void ffi_cache_destroy(prt::Cache* cache);
+
+struct AbstractBinaryDataBinding {
+	void (*set_data)(void* ctx, const uint8_t* data, size_t size);
+	void* context; // the actual Rust implementation
+};
+
+// reads the raw (undecoded) data of a resolve map key or a URI, e.g. a texture file of a rule package
+prt::Status ffi_read_binary_data(const char* keyOrUri, const prt::ResolveMap* resolveMap, prt::Cache* cache,
+                                 AbstractBinaryDataBinding* dataBinding);
+
+struct AbstractGeometryBinding {
+	void (*set_geometry)(void* ctx, const double* vertexCoords, size_t vertexCoordsCount, const uint32_t* indices,
+	                     size_t indicesCount, const uint32_t* faceCounts, size_t faceCountsCount,
+	                     const uint32_t* holes, size_t holesCount, const char* decoderId);
+	void* context; // the actual Rust implementation
+};
+
+// decodes the geometry at uri and passes it to the binding (together with the decoder id, also on error)
+prt::Status ffi_resolve_geometry(const char* uri, const prt::ResolveMap* resolveMap, prt::Cache* cache,
+                                 AbstractGeometryBinding* geometryBinding);
+
+enum CodecKind : int32_t { CODEC_ENCODER, CODEC_DECODER };
+
+struct CodecInfoWrapper {
+	const char* id;
+	const char* name;
+	const char* description;
+	const char* extensions;      // separated by ';'
+	const int32_t* contentTypes; // prt::ContentType
+	size_t contentTypeCount;
+};
+
+struct AbstractCodecInfoBinding {
+	void (*add_codec_info)(void* ctx, const CodecInfoWrapper* info);
+	void* context; // the actual Rust implementation
+};
+
+// passes the info of all codecs of the given kind to the binding, or only the one of codecId if not null
+prt::Status ffi_get_codec_infos(CodecKind kind, const char* codecId, AbstractCodecInfoBinding* codecInfoBinding);
+
 } // extern "C"
 
-class RustCallbacksBinding : public prt::SimpleOutputCallbacks {
+class RustCallbacksBinding : public prt::SimpleOutputCallbacks, public GeometryCaptureCallbacks {
 public:
-	explicit RustCallbacksBinding(AbstractCallbacksBinding* binding);
+	RustCallbacksBinding(AbstractCallbacksBinding* binding, size_t initialShapeCount);
 	virtual ~RustCallbacksBinding() = default;
 
 	prt::Status generateError(size_t isIndex, prt::Status status, const wchar_t* message) override;
@@ -85,51 +222,76 @@ public:
 	                            size_t size, size_t nRows) override;
 
 	bool canSeek() const override {
-		return mDelegate->canSeek();
+		return delegate().canSeek();
 	}
 	uint64_t open(const wchar_t* encoderId, const prt::ContentType contentType, const wchar_t* name,
 	              StringEncoding enc = SE_NATIVE, OpenMode mode = OPENMODE_ALWAYS, prt::Status* stat = 0) override {
-		return mDelegate->open(encoderId, contentType, name, enc, mode, stat);
+		return delegate().open(encoderId, contentType, name, enc, mode, stat);
 	}
 	prt::Status write(uint64_t handle, const wchar_t* string) override {
-		return mDelegate->write(handle, string);
+		if (isCanceled())
+			return prt::STATUS_CANCELED;
+		return delegate().write(handle, string);
 	};
 	prt::Status write(uint64_t handle, const uint8_t* buffer, size_t size) override {
-		return mDelegate->write(handle, buffer, size);
+		if (isCanceled())
+			return prt::STATUS_CANCELED;
+		return delegate().write(handle, buffer, size);
 	};
 	prt::Status seek(uint64_t handle, int64_t offset, SeekOrigin origin) override {
-		return mDelegate->seek(handle, offset, origin);
+		return delegate().seek(handle, offset, origin);
 	};
 	uint64_t tell(uint64_t handle, prt::Status* stat = 0) override {
-		return mDelegate->tell(handle, stat);
-	};
-	prt::Status close(uint64_t handle, const size_t* isIndices, size_t isCount) override {
-		return mDelegate->close(handle, isIndices, isCount);
+		return delegate().tell(handle, stat);
 	};
+	prt::Status close(uint64_t handle, const size_t* isIndices, size_t isCount) override;
 
 	prt::Status openCGAError(const wchar_t* name) override {
-		return mDelegate->openCGAError(name);
+		return delegate().openCGAError(name);
 	};
 	prt::Status openCGAPrint(const wchar_t* name) override {
-		return mDelegate->openCGAPrint(name);
+		return delegate().openCGAPrint(name);
 	};
 	prt::Status openCGAReport(const wchar_t* name) override {
-		return mDelegate->openCGAReport(name);
+		return delegate().openCGAReport(name);
 	};
 
 	prt::Status closeCGAError() override {
-		return mDelegate->closeCGAError();
+		return delegate().closeCGAError();
 	};
 	prt::Status closeCGAPrint() override {
-		return mDelegate->closeCGAPrint();
+		return delegate().closeCGAPrint();
 	};
 	prt::Status closeCGAReport() override {
-		return mDelegate->closeCGAReport();
+		return delegate().closeCGAReport();
 	};
 
+	prt::Status addMesh(size_t isIndex, const CapturedMeshWrapper* mesh) override;
+
+	bool isCanceled() const;
+
+	// reports all shapes which are not done yet as finished (or as failed with a status other than OK),
+	// to be called after generate returned
+	void finishProgress(prt::Status status);
+
 private:
-	std::unique_ptr<AbstractCallbacksBinding> mBinding;
+	void reportStarted(size_t isIndex);
+	void reportFinished(size_t isIndex, prt::Status status);
+	void reportProgress(size_t isIndex, ProgressState state, prt::Status status);
+	void reportValue(size_t isIndex, const wchar_t* key, const PrimitiveWrapper& value);
+	void attributeValue(size_t isIndex, const wchar_t* key, const PrimitiveWrapper& value);
+
+	// created on first use, generate calls without file output do not touch the file system
+	prt::FileOutputCallbacks& delegate();
+
+	AbstractCallbacksBinding* mBinding; // owned by the Rust side, outlives the generate call
+	std::once_flag mDelegateOnce;
 	std::unique_ptr<prt::FileOutputCallbacks, PRTObjectDestroyer> mDelegate;
+
+	// callbacks are invoked from the PRT worker threads, calls into the Rust callbacks are serialized
+	mutable std::mutex mCallbacksMutex;
+	std::vector<uint8_t> mShapeStates; // 0: not seen, 1: started, 2: done
+	std::chrono::steady_clock::time_point mStartTime;
 };
 
 extern "C" {
@@ -139,8 +301,10 @@ struct AbstractLogHandlerBinding {
 	void* context; // the actual Rust implementation
 };
 
-void ffi_add_log_handler(AbstractLogHandlerBinding* logHandler);
-void ffi_remove_log_handler(AbstractLogHandlerBinding* logHandler);
+// the binding stays owned by the Rust side, returns false if a handler with the same context is already added
+bool ffi_add_log_handler(AbstractLogHandlerBinding* logHandler);
+// returns the binding passed to ffi_add_log_handler (or null), PRT does not call into it anymore
+AbstractLogHandlerBinding* ffi_remove_log_handler(void* context);
 
 } // extern "C"
 
@@ -153,6 +317,10 @@ public:
 	const prt::LogLevel* getLevels(size_t* count) override;
 	void getFormat(bool* dateTime, bool* level) override;
 
+	// waits for a running handleLogEvent, the binding is not called afterwards
+	AbstractLogHandlerBinding* detach();
+
 private:
-	std::unique_ptr<AbstractLogHandlerBinding> mBinding;
+	AbstractLogHandlerBinding* mBinding; // owned by the Rust side, null once detached
+	std::mutex mMutex; // PRT logs from its worker threads, calls into the Rust log handler are serialized
 };
\ No newline at end of file
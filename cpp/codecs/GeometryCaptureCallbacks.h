@@ -0,0 +1,67 @@
+#pragma once
+
+#include "prt/Status.h"
+
+#include <cstddef>
+#include <cstdint>
+
+// shared between the bindings and the codecs extension, keep in sync with prt_ffi in lib.rs
+
+extern "C" {
+
+struct CapturedUVSetWrapper {
+	const double* uvs; // u, v, u, v, ...
+	size_t uvsCount;
+	const uint32_t* uvIndices;
+	size_t uvIndicesCount;
+	const uint32_t* faceUVCounts; // 0 for faces without uvs in this set
+	size_t faceUVCountsCount;
+};
+
+struct CapturedTextureWrapper {
+	const char* key; // the CGA material key, e.g. "colormap"
+	const char* uri;
+	uint32_t uvSet;
+};
+
+struct CapturedMaterialWrapper {
+	double color[3]; // r, g, b in [0, 1]
+	double opacity;
+	double metallic;
+	double roughness;
+	const CapturedTextureWrapper* textures; // only the texture maps which are set
+	size_t texturesCount;
+};
+
+struct CapturedMeshWrapper {
+	const double* vertexCoords;
+	size_t vertexCoordsCount;
+	const double* normals;
+	size_t normalsCount;
+	const uint32_t* faceCounts;
+	size_t faceCountsCount;
+	const uint32_t* vertexIndices;
+	size_t vertexIndicesCount;
+	const uint32_t* normalIndices;
+	size_t normalIndicesCount;
+	const CapturedUVSetWrapper* uvSets; // the index in this array is the uv set
+	size_t uvSetsCount;
+	const CapturedMaterialWrapper* material; // null if the mesh has no material
+};
+
+} // extern "C"
+
+constexpr const wchar_t* GEOMETRY_CAPTURE_ENCODER_ID = L"com.prust.codecs.GeometryCaptureEncoder";
+
+// the address of the GeometryCaptureCallbacks as hex string, set by the bindings for every generate call. The
+// encoder does not dynamic_cast the prt::Callbacks, the type info is not shared between the bindings and the
+// codecs library.
+constexpr const wchar_t* EO_CAPTURE_CALLBACKS = L"captureCallbacks";
+
+// implemented by the prt::Callbacks passed to generate
+class GeometryCaptureCallbacks {
+public:
+	virtual ~GeometryCaptureCallbacks() = default;
+
+	virtual prt::Status addMesh(size_t isIndex, const CapturedMeshWrapper* mesh) = 0;
+};
@@ -0,0 +1,233 @@
+#include "GeometryCaptureEncoder.h"
+#include "GeometryCaptureCallbacks.h"
+
+#include "prtx/EncoderInfoBuilder.h"
+#include "prtx/Exception.h"
+#include "prtx/GenerateContext.h"
+#include "prtx/Geometry.h"
+#include "prtx/Material.h"
+#include "prtx/Mesh.h"
+#include "prtx/PRTUtils.h"
+#include "prtx/Shape.h"
+#include "prtx/ShapeIterator.h"
+
+#include "prt/StringUtils.h"
+
+#include <cstdint>
+#include <cwchar>
+#include <iterator>
+#include <memory>
+#include <string>
+#include <vector>
+
+namespace {
+
+const wchar_t* EO_TRIANGULATE = L"triangulate";
+
+GeometryCaptureCallbacks* getCaptureCallbacks(const prt::AttributeMap* options) {
+	if (options == nullptr || !options->hasKey(EO_CAPTURE_CALLBACKS))
+		return nullptr;
+	const wchar_t* address = options->getString(EO_CAPTURE_CALLBACKS);
+	if (address == nullptr || *address == L'\0')
+		return nullptr;
+	return reinterpret_cast<GeometryCaptureCallbacks*>(static_cast<uintptr_t>(std::wcstoull(address, nullptr, 16)));
+}
+
+prtx::EncodePreparator::PreparationFlags getPreparationFlags(bool triangulate) {
+	return prtx::EncodePreparator::PreparationFlags()
+	        .instancing(false)
+	        .mergeByMaterial(false)
+	        .triangulate(triangulate)
+	        .processHoles(prtx::HoleProcessor::TRIANGULATE_FACES_WITH_HOLES)
+	        .mergeVertices(true)
+	        .cleanupVertexNormals(true)
+	        .cleanupUVs(true)
+	        .processVertexNormals(prtx::VertexNormalProcessor::SET_MISSING_TO_FACE_NORMALS)
+	        .indexSharing(prtx::EncodePreparator::PreparationFlags::INDICES_SEPARATE_FOR_ALL_VERTEX_ATTRIBUTES);
+}
+
+std::string toUTF8FromUTF16(const std::wstring& utf16String) {
+	std::vector<char> buffer(utf16String.size() + 1);
+	size_t size = buffer.size();
+	prt::StringUtils::toUTF8FromUTF16(utf16String.c_str(), buffer.data(), &size, nullptr);
+	if (size > buffer.size()) {
+		buffer.resize(size);
+		prt::StringUtils::toUTF8FromUTF16(utf16String.c_str(), buffer.data(), &size, nullptr);
+	}
+	return std::string{buffer.data()};
+}
+
+struct TextureMap {
+	const wchar_t* cgaKey;
+	const wchar_t* materialKey;
+	size_t layer; // index in the texture array of the material key
+};
+
+// the index in this array is the uv set of the texture map (see the CGA "material" attribute)
+const TextureMap TEXTURE_MAPS[] = {
+        {L"colormap", L"diffuseMap", 0},       {L"bumpmap", L"bumpMap", 0},
+        {L"dirtmap", L"diffuseMap", 1},        {L"specularmap", L"specularMap", 0},
+        {L"opacitymap", L"opacityMap", 0},     {L"normalmap", L"normalMap", 0},
+        {L"emissivemap", L"emissiveMap", 0},   {L"occlusionmap", L"occlusionMap", 0},
+        {L"roughnessmap", L"roughnessMap", 0}, {L"metallicmap", L"metallicMap", 0},
+};
+
+struct CapturedMaterial {
+	std::vector<std::string> keys;
+	std::vector<std::string> uris;
+	std::vector<CapturedTextureWrapper> textures;
+	CapturedMaterialWrapper wrapper;
+
+	explicit CapturedMaterial(const prtx::Material& material) : wrapper{} {
+		const prtx::DoubleVector& color = material.getFloatArray(L"diffuseColor");
+		for (size_t i = 0; i < 3 && i < color.size(); i++)
+			wrapper.color[i] = color[i];
+		wrapper.opacity = material.getFloat(L"opacity");
+		wrapper.metallic = material.getFloat(L"metallic");
+		wrapper.roughness = material.getFloat(L"roughness");
+
+		std::vector<uint32_t> uvSets;
+		for (uint32_t uvSet = 0; uvSet < std::size(TEXTURE_MAPS); uvSet++) {
+			const TextureMap& map = TEXTURE_MAPS[uvSet];
+			const prtx::TexturePtrVector& maps = material.getTextureArray(map.materialKey);
+			if (map.layer >= maps.size() || !maps[map.layer] || !maps[map.layer]->isValid())
+				continue;
+			keys.push_back(toUTF8FromUTF16(map.cgaKey));
+			uris.push_back(toUTF8FromUTF16(maps[map.layer]->getURI()->wstring()));
+			uvSets.push_back(uvSet);
+		}
+
+		// the strings must not move anymore once we point to them
+		textures.reserve(keys.size());
+		for (size_t i = 0; i < keys.size(); i++)
+			textures.push_back({keys[i].c_str(), uris[i].c_str(), uvSets[i]});
+		wrapper.textures = textures.data();
+		wrapper.texturesCount = textures.size();
+	}
+};
+
+struct CapturedUVSet {
+	std::vector<uint32_t> uvIndices;
+	std::vector<uint32_t> faceUVCounts;
+};
+
+prt::Status captureMesh(GeometryCaptureCallbacks& callbacks, size_t isIndex, const prtx::Mesh& mesh,
+                        const prtx::MaterialPtr& material) {
+	const uint32_t faceCount = mesh.getFaceCount();
+	const uint32_t uvSetsCount = mesh.getUVSetsCount();
+
+	std::vector<uint32_t> faceCounts;
+	std::vector<uint32_t> vertexIndices;
+	std::vector<uint32_t> normalIndices;
+	std::vector<CapturedUVSet> uvSets(uvSetsCount);
+	faceCounts.reserve(faceCount);
+	for (uint32_t fi = 0; fi < faceCount; fi++) {
+		const uint32_t vertexCount = mesh.getFaceVertexCount(fi);
+		faceCounts.push_back(vertexCount);
+
+		const uint32_t* faceVertexIndices = mesh.getFaceVertexIndices(fi);
+		vertexIndices.insert(vertexIndices.end(), faceVertexIndices, faceVertexIndices + vertexCount);
+
+		// all faces have normals thanks to SET_MISSING_TO_FACE_NORMALS
+		const uint32_t* faceNormalIndices = mesh.getFaceVertexNormalIndices(fi);
+		normalIndices.insert(normalIndices.end(), faceNormalIndices, faceNormalIndices + vertexCount);
+
+		for (uint32_t uvSet = 0; uvSet < uvSetsCount; uvSet++) {
+			const uint32_t uvCount = mesh.getFaceUVCount(fi, uvSet);
+			uvSets[uvSet].faceUVCounts.push_back(uvCount);
+			if (uvCount > 0) {
+				const uint32_t* faceUVIndices = mesh.getFaceUVIndices(fi, uvSet);
+				uvSets[uvSet].uvIndices.insert(uvSets[uvSet].uvIndices.end(), faceUVIndices,
+				                               faceUVIndices + uvCount);
+			}
+		}
+	}
+
+	std::vector<CapturedUVSetWrapper> uvSetWrappers;
+	uvSetWrappers.reserve(uvSetsCount);
+	for (uint32_t uvSet = 0; uvSet < uvSetsCount; uvSet++) {
+		const prtx::DoubleVector& uvs = mesh.getUVCoords(uvSet);
+		const CapturedUVSet& s = uvSets[uvSet];
+		uvSetWrappers.push_back({uvs.data(), uvs.size(), s.uvIndices.data(), s.uvIndices.size(),
+		                         s.faceUVCounts.data(), s.faceUVCounts.size()});
+	}
+
+	const std::unique_ptr<CapturedMaterial> capturedMaterial =
+	        material ? std::make_unique<CapturedMaterial>(*material) : nullptr;
+
+	const prtx::DoubleVector& vertexCoords = mesh.getVertexCoords();
+	const prtx::DoubleVector& normals = mesh.getVertexNormalsCoords();
+	const CapturedMeshWrapper wrapper{vertexCoords.data(),
+	                                  vertexCoords.size(),
+	                                  normals.data(),
+	                                  normals.size(),
+	                                  faceCounts.data(),
+	                                  faceCounts.size(),
+	                                  vertexIndices.data(),
+	                                  vertexIndices.size(),
+	                                  normalIndices.data(),
+	                                  normalIndices.size(),
+	                                  uvSetWrappers.data(),
+	                                  uvSetWrappers.size(),
+	                                  capturedMaterial ? &capturedMaterial->wrapper : nullptr};
+	return callbacks.addMesh(isIndex, &wrapper);
+}
+
+} // namespace
+
+const std::wstring GeometryCaptureEncoder::ID = GEOMETRY_CAPTURE_ENCODER_ID;
+const std::wstring GeometryCaptureEncoder::NAME = L"Rust Geometry Capture Encoder";
+const std::wstring GeometryCaptureEncoder::DESCRIPTION = L"Passes the generated meshes to the Rust callbacks.";
+
+GeometryCaptureEncoder::GeometryCaptureEncoder(const std::wstring& id, const prt::AttributeMap* options,
+                                               prt::Callbacks* callbacks)
+    : prtx::GeometryEncoder(id, options, callbacks) {}
+
+void GeometryCaptureEncoder::init(prtx::GenerateContext& /*context*/) {
+	const prtx::NamePreparator::NamespacePtr nsMesh = mNamePreparator.newNamespace();
+	const prtx::NamePreparator::NamespacePtr nsMaterial = mNamePreparator.newNamespace();
+	mEncodePreparator = prtx::EncodePreparator::create(true, mNamePreparator, nsMesh, nsMaterial);
+}
+
+void GeometryCaptureEncoder::encode(prtx::GenerateContext& context, size_t initialShapeIndex) {
+	GeometryCaptureCallbacks* callbacks = getCaptureCallbacks(getOptions());
+	if (callbacks == nullptr)
+		throw prtx::StatusException(prt::STATUS_ILLEGAL_CALLBACK_OBJECT);
+
+	// errors (including cancellation) propagate, PRT reports the initial shape as failed
+	const prtx::InitialShape& initialShape = *context.getInitialShape(initialShapeIndex);
+	const prtx::LeafIteratorPtr leafIterator = prtx::LeafIterator::create(context, initialShapeIndex);
+	for (prtx::ShapePtr shape = leafIterator->getNext(); shape; shape = leafIterator->getNext())
+		mEncodePreparator->add(context.getCache(), shape, initialShape.getAttributeMap());
+
+	const bool triangulate = getOptions()->getBool(EO_TRIANGULATE);
+	prtx::EncodePreparator::InstanceVector instances;
+	mEncodePreparator->fetchFinalizedInstances(instances, getPreparationFlags(triangulate));
+	for (const auto& instance : instances) {
+		const prtx::MeshPtrVector& meshes = instance.getGeometry()->getMeshes();
+		const prtx::MaterialPtrVector& materials = instance.getMaterials(); // one per mesh
+		for (size_t mi = 0; mi < meshes.size(); mi++) {
+			const prtx::MaterialPtr material = (mi < materials.size()) ? materials[mi] : prtx::MaterialPtr();
+			const prt::Status status = captureMesh(*callbacks, initialShapeIndex, *meshes[mi], material);
+			if (status != prt::STATUS_OK)
+				throw prtx::StatusException(status);
+		}
+	}
+}
+
+void GeometryCaptureEncoder::finish(prtx::GenerateContext& /*context*/) {}
+
+GeometryCaptureEncoderFactory* GeometryCaptureEncoderFactory::createInstance() {
+	prtx::EncoderInfoBuilder encoderInfoBuilder;
+	encoderInfoBuilder.setID(GeometryCaptureEncoder::ID);
+	encoderInfoBuilder.setName(GeometryCaptureEncoder::NAME);
+	encoderInfoBuilder.setDescription(GeometryCaptureEncoder::DESCRIPTION);
+	encoderInfoBuilder.setType(prt::CT_GEOMETRY);
+
+	prtx::PRTUtils::AttributeMapBuilderPtr amb(prt::AttributeMapBuilder::create());
+	amb->setBool(EO_TRIANGULATE, false);
+	amb->setString(EO_CAPTURE_CALLBACKS, L"");
+	encoderInfoBuilder.setDefaultOptions(amb->createAttributeMap());
+
+	return new GeometryCaptureEncoderFactory(encoderInfoBuilder.create());
+}
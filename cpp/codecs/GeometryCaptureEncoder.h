@@ -0,0 +1,40 @@
+#pragma once
+
+#include "prtx/EncodePreparator.h"
+#include "prtx/Encoder.h"
+#include "prtx/EncoderFactory.h"
+#include "prtx/Singleton.h"
+
+#include <string>
+
+// passes the generated meshes to the GeometryCaptureCallbacks instead of writing files
+class GeometryCaptureEncoder : public prtx::GeometryEncoder {
+public:
+	static const std::wstring ID;
+	static const std::wstring NAME;
+	static const std::wstring DESCRIPTION;
+
+	GeometryCaptureEncoder(const std::wstring& id, const prt::AttributeMap* options, prt::Callbacks* callbacks);
+	~GeometryCaptureEncoder() override = default;
+
+	void init(prtx::GenerateContext& context) override;
+	void encode(prtx::GenerateContext& context, size_t initialShapeIndex) override;
+	void finish(prtx::GenerateContext& context) override;
+
+private:
+	prtx::NamePreparator mNamePreparator;
+	prtx::EncodePreparatorPtr mEncodePreparator;
+};
+
+class GeometryCaptureEncoderFactory : public prtx::EncoderFactory,
+                                      public prtx::Singleton<GeometryCaptureEncoderFactory> {
+public:
+	static GeometryCaptureEncoderFactory* createInstance();
+
+	explicit GeometryCaptureEncoderFactory(const prt::EncoderInfo* info) : prtx::EncoderFactory(info) {}
+	~GeometryCaptureEncoderFactory() override = default;
+
+	GeometryCaptureEncoder* create(const prt::AttributeMap* options, prt::Callbacks* callbacks) const override {
+		return new GeometryCaptureEncoder(getID(), options, callbacks);
+	}
+};
@@ -0,0 +1,29 @@
+#include "GeometryCaptureEncoder.h"
+
+#include "prtx/ExtensionManager.h"
+
+#ifdef _WIN32
+#	define PRUST_CODECS_EXPORTS_API __declspec(dllexport)
+#else
+#	define PRUST_CODECS_EXPORTS_API __attribute__((visibility("default")))
+#endif
+
+// entry points called by PRT when loading the extension from the plugin path
+
+extern "C" {
+
+PRUST_CODECS_EXPORTS_API void registerExtensionFactories(prtx::ExtensionManager* manager) {
+	manager->addFactory(GeometryCaptureEncoderFactory::createInstance());
+}
+
+PRUST_CODECS_EXPORTS_API void unregisterExtensionFactories(prtx::ExtensionManager* /*manager*/) {}
+
+PRUST_CODECS_EXPORTS_API int getVersionMajor() {
+	return PRT_VERSION_MAJOR;
+}
+
+PRUST_CODECS_EXPORTS_API int getVersionMinor() {
+	return PRT_VERSION_MINOR;
+}
+
+} // extern "C"
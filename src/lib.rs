@@ -1,8 +1,49 @@
+/// Bindings for the Procedural Runtime (PRT) of ArcGIS CityEngine.
+///
+/// # Thread safety
+///
+/// PRT is initialized once per process with [`prt::init`], all other functions may be called from any thread,
+/// including concurrent generate calls (e.g. from several async workers or with a [`prt::BatchGenerator`]):
+///
+/// - [`prt::PrtContext`], [`prt::ResolveMap`] and [`prt::Cache`] are `Send` and `Sync`, e.g. share a cache
+///   between threads with an `Arc`.
+/// - [`prt::OcclusionSet`] is `Send` only, the occluders of a batch are generated and queried by one thread.
+/// - Initial shapes, options and results are plain data and `Send` and `Sync`.
+/// - The callbacks of a generate call are invoked from the PRT worker threads, hence they must be `Send`.
+///   The calls are serialized, i.e. they do not need to be `Sync`.
+/// - File output goes into [`prt::Callbacks::output_directory`]. If not set, every generate call writes into
+///   a new directory below `<temp>/prust` (its path is logged at [`prt::LogLevel::LOG_INFO`]), so concurrent
+///   calls do not overwrite each other's files. These directories are not removed.
+/// - Log handlers are global and receive the messages of all threads, hence they must be `Send`. The calls into
+///   a single handler are serialized.
 pub mod prt {
+    pub mod batch;
+    pub mod capture;
+    mod conversions;
+    pub mod coordinates;
+    pub mod encoders;
+    #[cfg(feature = "geo")]
+    pub mod geo;
+    #[cfg(feature = "geojson")]
+    pub mod geojson;
+    pub mod seed;
+    pub mod uri;
+    pub mod validation;
+
+    pub use batch::{BatchGenerator, BatchGeneratorBuilder, ChunkResult};
+    pub use capture::{CapturedMaterial, CapturedMesh, CapturedTexture, CapturedUvSet, GeometryConsumer};
+    pub use coordinates::{AxisConvention, FootprintPolygon, LocalFrame};
+    pub use seed::SeedStrategy;
+    pub use uri::PrtUri;
+    pub use validation::GeometryIssue;
+
     use std::{collections, fmt, path};
     use std::ffi;
     use std::fmt::{Display, Formatter};
     use std::ptr;
+    use std::sync::{mpsc, Arc};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time;
     use derive_builder::Builder;
 
     #[derive(Debug)]
@@ -15,6 +56,7 @@ pub mod prt {
         handle: *const prt_ffi::Object,
     }
 
+    unsafe impl Send for PrtContext {}
     unsafe impl Sync for PrtContext {} // handle is thread-safe
 
     impl Drop for PrtContext {
@@ -44,10 +86,14 @@ pub mod prt {
         }
         let cesdk_lib_dir_wchar_vec = crate::helpers::from_string_to_wchar_vec(cesdk_lib_path.to_str().unwrap());
 
+        // the prust codecs (e.g. the geometry capture encoder) are built together with the bindings
+        let codecs_dir_wchar_vec = crate::helpers::from_string_to_wchar_vec(
+            crate::helpers::get_codecs_path().to_str().unwrap());
+
         // append additional extension dirs
         // TODO: handle extra_plugin_paths
 
-        let plugins_dirs: [*const libc::wchar_t; 1] = [cesdk_lib_dir_wchar_vec.as_ptr()];
+        let plugins_dirs: [*const libc::wchar_t; 2] = [cesdk_lib_dir_wchar_vec.as_ptr(), codecs_dir_wchar_vec.as_ptr()];
         let log_level = initial_minimal_log_level.or(Some(LogLevel::LOG_WARNING));
         unsafe {
             let mut status = Status::STATUS_UNSPECIFIED_ERROR;
@@ -67,8 +113,9 @@ pub mod prt {
         }
     }
 
-    #[derive(PartialEq)]
+    #[derive(Clone, PartialEq)]
     #[derive(Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub enum PrimitiveType {
         Undefined(),
         String(String),
@@ -79,15 +126,91 @@ pub mod prt {
         FloatArray(Vec<f64>),
         BoolArray(Vec<bool>),
         IntArray(Vec<i32>),
+        StringArray2D(Array2D<String>),
+        FloatArray2D(Array2D<f64>),
+        BoolArray2D(Array2D<bool>),
+        IntArray2D(Array2D<i32>),
+    }
+
+    /// A CGA 2D array, the values are stored row by row. PRT reports plain arrays with a single row, i.e. a 2D
+    /// array with one row is passed to PRT like a plain array and comes back as one.
+    #[derive(Clone, Debug, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize),
+               serde(try_from = "Array2DFields<T>"))]
+    pub struct Array2D<T> {
+        values: Vec<T>,
+        rows: usize,
+    }
+
+    impl<T> Array2D<T> {
+        /// Returns `None` if there are no rows or the values cannot be split into the given number of rows.
+        pub fn new(values: Vec<T>, rows: usize) -> Option<Array2D<T>> {
+            (rows > 0 && values.len().is_multiple_of(rows)).then_some(Array2D { values, rows })
+        }
+
+        pub fn rows(&self) -> usize {
+            self.rows
+        }
+
+        pub fn columns(&self) -> usize {
+            self.values.len() / self.rows
+        }
+
+        pub fn row(&self, row: usize) -> Option<&[T]> {
+            if row >= self.rows {
+                return None;
+            }
+            let start = row.checked_mul(self.columns())?;
+            self.values.get(start..start.checked_add(self.columns())?)
+        }
+
+        pub fn get(&self, row: usize, column: usize) -> Option<&T> {
+            self.row(row).and_then(|r| r.get(column))
+        }
+
+        /// All values, row by row.
+        pub fn values(&self) -> &[T] {
+            &self.values
+        }
+
+        pub fn into_values(self) -> Vec<T> {
+            self.values
+        }
+    }
+
+    // checks the number of rows when deserializing an Array2D
+    #[cfg(feature = "serde")]
+    #[derive(serde::Deserialize)]
+    struct Array2DFields<T> {
+        values: Vec<T>,
+        rows: usize,
+    }
+
+    #[cfg(feature = "serde")]
+    impl<T> TryFrom<Array2DFields<T>> for Array2D<T> {
+        type Error = String;
+
+        fn try_from(fields: Array2DFields<T>) -> Result<Self, Self::Error> {
+            let value_count = fields.values.len();
+            Array2D::new(fields.values, fields.rows)
+                .ok_or_else(|| format!("{} values cannot be split into {} rows", value_count, fields.rows))
+        }
     }
 
     pub type EncoderOptions = collections::HashMap<String, PrimitiveType>;
 
     #[derive(Clone, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub enum KeyOrUri {
         Undefined,
         Key(String),
-        Uri(String), // TODO: use a type which supports nested Uris
+        Uri(PrtUri),
+    }
+
+    impl From<PrtUri> for KeyOrUri {
+        fn from(uri: PrtUri) -> Self {
+            KeyOrUri::Uri(uri)
+        }
     }
 
     impl Default for KeyOrUri {
@@ -95,12 +218,14 @@ pub mod prt {
     }
 
     impl KeyOrUri {
-        fn ffi_to_cstring(&self) -> ffi::CString {
-            match self {
-                KeyOrUri::Undefined => panic!("Undefined ResolveMap key or Uri!"),
-                KeyOrUri::Key(k) => ffi::CString::new(k.as_str()).unwrap(),
-                KeyOrUri::Uri(u) => ffi::CString::new(u.as_str()).unwrap(),
-            }
+        // the error is the reason why the key or URI cannot be passed to PRT
+        fn ffi_to_cstring(&self) -> Result<ffi::CString, &'static str> {
+            let value = match self {
+                KeyOrUri::Undefined => return Err("is undefined"),
+                KeyOrUri::Key(k) => k.clone(),
+                KeyOrUri::Uri(u) => u.to_string(),
+            };
+            ffi::CString::new(value).map_err(|_| "contains a NUL byte")
         }
     }
 
@@ -114,40 +239,823 @@ pub mod prt {
         }
     }
 
+    /// The holes of a face, given as the indices of other faces of the same initial shape.
+    #[derive(Clone, Debug, Default, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct FaceHoles {
+        pub face: u32,
+        pub holes: Vec<u32>,
+    }
+
+    /// A set of texture coordinates, the arrays correspond to the ones of `prt::InitialShapeBuilder::setUVs`.
+    #[derive(Clone, Debug, Default, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct UvSet {
+        /// Pairs of u and v coordinates.
+        pub uvs: Vec<f64>,
+        /// Index into the uv pairs for each face vertex.
+        pub uv_indices: Vec<u32>,
+        pub face_ranges: Vec<u32>,
+    }
+
+    /// With the `serde` feature, deserialization goes through the builder and applies the same validation.
     #[derive(Default, Builder, Debug)]
+    #[builder(build_fn(validate = "Self::validate"))]
+    #[cfg_attr(feature = "serde", builder(derive(serde::Deserialize)))]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize),
+               serde(try_from = "InitialShapeBuilder"))]
     pub struct InitialShape {
         vertex_coords: Vec<f64>,
         indices: Vec<u32>,
         face_counts: Vec<u32>,
+        #[builder(default)]
+        holes: Vec<FaceHoles>,
+        /// The uv sets in the order of their index, i.e. the first one becomes uv set 0.
+        #[builder(default)]
+        uv_sets: Vec<UvSet>,
+        #[builder(default)]
+        textures: Vec<KeyOrUri>,
+        #[builder(default)]
+        texture_face_ranges: Vec<u32>,
 
         rule_file: KeyOrUri,
         start_rule: String,
+        /// Either set explicitly or derived with [`InitialShapeBuilder::seed_strategy`].
+        #[builder(setter(custom), field(ty = "Option<seed::SeedSource>", build = "self.build_random_seed()?"))]
         random_seed: i32,
         name: String,
+        /// Rule attribute values keyed by their qualified name (e.g. "Default$height"), overriding the rule defaults.
+        #[builder(default)]
+        attributes: collections::HashMap<String, PrimitiveType>,
+        /// The frame the geometry has been mapped into from world coordinates, see
+        /// [`InitialShapeBuilder::footprints`]. Not passed to PRT.
+        #[builder(default)]
+        local_frame: Option<LocalFrame>,
+    }
+
+    impl InitialShape {
+        pub fn local_frame(&self) -> Option<&LocalFrame> {
+            self.local_frame.as_ref()
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    impl TryFrom<InitialShapeBuilder> for InitialShape {
+        type Error = InitialShapeBuilderError;
+
+        fn try_from(builder: InitialShapeBuilder) -> Result<Self, Self::Error> {
+            builder.build()
+        }
+    }
+
+    impl InitialShapeBuilder {
+        pub fn random_seed(&mut self, value: i32) -> &mut Self {
+            self.random_seed = Some(seed::SeedSource::Value(value));
+            self
+        }
+
+        /// Derives the random seed from the initial shape when it is built, instead of setting it explicitly.
+        /// The seed stays the same across runs and machines, e.g. the same parcel always looks the same.
+        pub fn seed_strategy(&mut self, strategy: SeedStrategy) -> &mut Self {
+            self.random_seed = Some(seed::SeedSource::Strategy(strategy));
+            self
+        }
+
+        fn build_random_seed(&self) -> Result<i32, InitialShapeBuilderError> {
+            match &self.random_seed {
+                Some(seed::SeedSource::Value(value)) => Ok(*value),
+                Some(seed::SeedSource::Strategy(strategy)) => Ok(strategy.seed(
+                    self.vertex_coords.as_deref().unwrap_or_default(),
+                    self.face_counts.as_deref().unwrap_or_default(),
+                    self.name.as_deref().unwrap_or_default(),
+                    self.local_frame.flatten().as_ref())),
+                None => Err(derive_builder::UninitializedFieldError::new("random_seed").into()),
+            }
+        }
+
+        fn validate(&self) -> Result<(), String> {
+            for (uv_set_index, uv_set) in self.uv_sets.iter().flatten().enumerate() {
+                if uv_set.uvs.len() % 2 != 0 {
+                    return Err(format!("UV set {} has an odd number of uv coordinates", uv_set_index));
+                }
+                let uv_count = (uv_set.uvs.len() / 2) as u32;
+                if let Some(uv_index) = uv_set.uv_indices.iter().find(|&&i| i >= uv_count) {
+                    return Err(format!("UV index {} of uv set {} is out of range (uv count is {})",
+                                       uv_index, uv_set_index, uv_count));
+                }
+            }
+
+            let face_count = self.face_counts.as_ref().map_or(0, |fc| fc.len()) as u32;
+            let holes = match self.holes.as_ref() {
+                Some(holes) => holes,
+                None => return Ok(()),
+            };
+
+            let mut faces_with_holes = collections::HashSet::new();
+            let mut hole_faces = collections::HashSet::new();
+            for face_holes in holes {
+                if face_holes.face >= face_count {
+                    return Err(format!("Face {} with holes does not exist (face count is {})",
+                                       face_holes.face, face_count));
+                }
+                if !faces_with_holes.insert(face_holes.face) {
+                    return Err(format!("Holes of face {} are defined more than once", face_holes.face));
+                }
+                for &hole in &face_holes.holes {
+                    if hole >= face_count {
+                        return Err(format!("Hole {} of face {} does not exist (face count is {})",
+                                           hole, face_holes.face, face_count));
+                    }
+                    if hole == face_holes.face {
+                        return Err(format!("Face {} cannot be a hole of itself", hole));
+                    }
+                    if !hole_faces.insert(hole) {
+                        return Err(format!("Face {} is used as a hole more than once", hole));
+                    }
+                }
+            }
+            if let Some(face) = faces_with_holes.intersection(&hole_faces).next() {
+                return Err(format!("Face {} is a hole and cannot have holes itself", face));
+            }
+            Ok(())
+        }
+    }
+
+    /// Shared flag to cooperatively cancel a running generate from another thread.
+    /// All clones of a token refer to the same flag.
+    #[derive(Clone, Debug, Default)]
+    pub struct CancellationToken {
+        canceled: Arc<AtomicBool>,
     }
 
-    pub trait Callbacks {}
+    impl CancellationToken {
+        pub fn new() -> CancellationToken {
+            CancellationToken::default()
+        }
+
+        pub fn cancel(&self) {
+            self.canceled.store(true, Ordering::Release);
+        }
+
+        pub fn is_canceled(&self) -> bool {
+            self.canceled.load(Ordering::Acquire)
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum ShapeProgress {
+        Started,
+        Finished,
+        Failed(Status),
+    }
+
+    /// Progress of a single initial shape within a generate call.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct ProgressEvent {
+        pub initial_shape_index: usize,
+        pub initial_shape_count: usize,
+        pub progress: ShapeProgress,
+        /// Time since the start of the generate call.
+        pub elapsed: time::Duration,
+    }
+
+    /// Receives the progress of the initial shapes. Every shape is reported as started exactly once
+    /// and eventually either as finished or failed, also if generating fails or is canceled (the remaining shapes
+    /// fail with that status). Nothing is reported if the generate call is rejected before it reaches PRT,
+    /// i.e. for invalid inputs or if it has already been canceled.
+    pub trait ProgressListener {
+        fn on_progress(&mut self, event: ProgressEvent);
+    }
+
+    /// Feeds the progress events into a channel, e.g. to drive a progress bar on another thread.
+    impl ProgressListener for mpsc::Sender<ProgressEvent> {
+        fn on_progress(&mut self, event: ProgressEvent) {
+            crate::helpers::send_or_discard(self, event);
+        }
+    }
+
+    pub trait Callbacks {
+        /// Polled by the native callbacks, generate aborts with `Status::STATUS_CANCELED` once this returns true.
+        /// Calls are serialized with the other callbacks, i.e. e.g. `on_progress` may request the cancellation.
+        fn is_canceled(&self) -> bool {
+            false
+        }
+
+        /// Called with the progress of the individual initial shapes, calls are serialized by the binding.
+        fn on_progress(&mut self, _event: ProgressEvent) {}
+
+        /// Called with the meshes of the geometry capture encoder, calls are serialized by the binding.
+        fn on_mesh(&mut self, _initial_shape_index: usize, _mesh: CapturedMesh) {}
+
+        /// Called with the CGA report values of the CGA report encoder, calls are serialized by the binding.
+        fn on_report(&mut self, _initial_shape_index: usize, _key: &str, _value: PrimitiveType) {}
+
+        /// Called with the final rule attribute values, e.g. of the attribute evaluation encoder.
+        /// Calls are serialized by the binding.
+        fn on_attribute(&mut self, _initial_shape_index: usize, _key: &str, _value: PrimitiveType) {}
+
+        /// The directory the file encoders write into, created if missing. Defaults to a new directory below
+        /// `<temp>/prust` per generate call.
+        fn output_directory(&self) -> Option<&path::Path> {
+            None
+        }
+    }
 
     #[derive(Default)]
-    pub struct FileCallbacks {}
+    pub struct FileCallbacks {
+        cancellation_token: Option<CancellationToken>,
+        progress_listener: Option<Box<dyn ProgressListener + Send>>,
+        geometry_consumer: Option<Box<dyn GeometryConsumer + Send>>,
+        output_directory: Option<path::PathBuf>,
+    }
+
+    impl FileCallbacks {
+        pub fn with_cancellation_token(mut self, cancellation_token: CancellationToken) -> FileCallbacks {
+            self.cancellation_token = Some(cancellation_token);
+            self
+        }
+
+        pub fn with_progress_listener<L>(mut self, progress_listener: L) -> FileCallbacks
+            where L: ProgressListener + Send + 'static
+        {
+            self.progress_listener = Some(Box::new(progress_listener));
+            self
+        }
+
+        pub fn with_geometry_consumer<G>(mut self, geometry_consumer: G) -> FileCallbacks
+            where G: GeometryConsumer + Send + 'static
+        {
+            self.geometry_consumer = Some(Box::new(geometry_consumer));
+            self
+        }
+
+        /// Writes the files of the encoders into `output_directory` instead of a new temporary directory.
+        /// Files of the same name are overwritten, i.e. use different directories for concurrent calls.
+        pub fn with_output_directory<P>(mut self, output_directory: P) -> FileCallbacks
+            where P: Into<path::PathBuf>
+        {
+            self.output_directory = Some(output_directory.into());
+            self
+        }
+    }
+
+    impl Callbacks for FileCallbacks {
+        fn is_canceled(&self) -> bool {
+            self.cancellation_token.as_ref().is_some_and(|t| t.is_canceled())
+        }
+
+        fn on_progress(&mut self, event: ProgressEvent) {
+            if let Some(listener) = self.progress_listener.as_mut() {
+                listener.on_progress(event);
+            }
+        }
+
+        fn on_mesh(&mut self, initial_shape_index: usize, mesh: CapturedMesh) {
+            if let Some(consumer) = self.geometry_consumer.as_mut() {
+                consumer.on_mesh(initial_shape_index, mesh);
+            }
+        }
+
+        fn output_directory(&self) -> Option<&path::Path> {
+            self.output_directory.as_deref()
+        }
+    }
+
+    /// The CGA report values of an initial shape.
+    pub type Reports = collections::HashMap<String, PrimitiveType>;
+
+    /// The final rule attribute values of an initial shape, keyed by the qualified name (e.g. "Default$height").
+    pub type AttributeValues = collections::HashMap<String, PrimitiveType>;
+
+    // collects the reports and attribute values, forwards everything else to the callbacks of the caller
+    struct ShapeValueCollector<'a, C: Callbacks> {
+        callbacks: &'a mut C,
+        reports: Vec<Reports>,
+        attributes: Vec<AttributeValues>,
+    }
+
+    impl<C: Callbacks> Callbacks for ShapeValueCollector<'_, C> {
+        fn is_canceled(&self) -> bool {
+            self.callbacks.is_canceled()
+        }
+
+        fn on_progress(&mut self, event: ProgressEvent) {
+            self.callbacks.on_progress(event);
+        }
+
+        fn on_mesh(&mut self, initial_shape_index: usize, mesh: CapturedMesh) {
+            self.callbacks.on_mesh(initial_shape_index, mesh);
+        }
+
+        fn on_report(&mut self, initial_shape_index: usize, key: &str, value: PrimitiveType) {
+            if let Some(reports) = self.reports.get_mut(initial_shape_index) {
+                reports.insert(key.to_string(), value);
+            }
+        }
+
+        fn on_attribute(&mut self, initial_shape_index: usize, key: &str, value: PrimitiveType) {
+            if let Some(attributes) = self.attributes.get_mut(initial_shape_index) {
+                attributes.insert(key.to_string(), value);
+            }
+        }
+
+        fn output_directory(&self) -> Option<&path::Path> {
+            self.callbacks.output_directory()
+        }
+    }
+
+    /// Owns a native `prt::ResolveMap`, e.g. the one of a rule package.
+    pub struct ResolveMap {
+        handle: *const prt_ffi::ResolveMap,
+    }
+
+    unsafe impl Send for ResolveMap {}
+    unsafe impl Sync for ResolveMap {} // immutable once created
+
+    impl ResolveMap {
+        /// Creates the resolve map of a rule package (or any other container PRT can read), e.g. `rpk:file:/x.rpk!/`.
+        pub fn from_uri(uri: &str) -> Result<ResolveMap, PrtError> {
+            let uri_cstring = ffi::CString::new(uri).map_err(|_| PrtError {
+                message: format!("Resolve map URI '{}' contains a NUL byte", uri),
+                status: Some(Status::STATUS_INVALID_URI),
+            })?;
+            let mut status = Status::STATUS_UNSPECIFIED_ERROR;
+            let handle = unsafe { prt_ffi::ffi_create_resolve_map(uri_cstring.as_ptr(), ptr::addr_of_mut!(status)) };
+            if handle.is_null() || status != Status::STATUS_OK {
+                return Err(PrtError {
+                    message: format!("Could not create resolve map for '{}': {}", uri, get_status_description(status)),
+                    status: Some(status),
+                });
+            }
+            Ok(ResolveMap { handle })
+        }
+
+        /// Reads the raw bytes of a key of this resolve map (e.g. `assets/facade.jpg`) or of any URI,
+        /// e.g. the texture files of [`CapturedTexture`].
+        pub fn read_bytes(&self, key_or_uri: &str, cache: Option<&Cache>) -> Result<Vec<u8>, PrtError> {
+            extern "C" fn set_data(context: *mut Vec<u8>, data: *const u8, size: libc::size_t) {
+                unsafe {
+                    *context = crate::helpers::from_raw_parts_to_vec(data, size);
+                }
+            }
+
+            let key_or_uri_cstring = ffi::CString::new(key_or_uri).map_err(|_| PrtError {
+                message: format!("Key or URI '{}' contains a NUL byte", key_or_uri),
+                status: Some(Status::STATUS_INVALID_URI),
+            })?;
+
+            let mut data: Vec<u8> = Vec::new();
+            let mut binding = prt_ffi::AbstractBinaryDataBinding {
+                set_data,
+                context: ptr::addr_of_mut!(data),
+            };
+            let status = unsafe {
+                prt_ffi::ffi_read_binary_data(key_or_uri_cstring.as_ptr(),
+                                              self.handle,
+                                              cache.map_or(ptr::null_mut(), |c| c.handle),
+                                              ptr::addr_of_mut!(binding) as *mut ffi::c_void)
+            };
+            if status != Status::STATUS_OK {
+                return Err(PrtError {
+                    message: format!("Could not read '{}': {}", key_or_uri, get_status_description(status)),
+                    status: Some(status),
+                });
+            }
+            Ok(data)
+        }
+    }
+
+    impl Drop for ResolveMap {
+        fn drop(&mut self) {
+            unsafe {
+                prt_ffi::ffi_resolve_map_destroy(self.handle);
+            }
+        }
+    }
+
+    /// Owns a native `prt::Cache`, sharing it between calls avoids decoding the same assets and rules again.
+    pub struct Cache {
+        handle: *mut prt_ffi::Cache,
+    }
+
+    unsafe impl Send for Cache {}
+    unsafe impl Sync for Cache {} // the PRT cache is thread-safe
+
+    impl Cache {
+        pub fn new() -> Result<Cache, PrtError> {
+            let handle = unsafe { prt_ffi::ffi_cache_create() };
+            if handle.is_null() {
+                return Err(PrtError {
+                    message: "Could not create cache".to_string(),
+                    status: None,
+                });
+            }
+            Ok(Cache { handle })
+        }
+    }
+
+    impl Drop for Cache {
+        fn drop(&mut self) {
+            unsafe {
+                prt_ffi::ffi_cache_destroy(self.handle);
+            }
+        }
+    }
+
+    /// Details about the geometry set by [`InitialShapeBuilder::resolve_geometry`].
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct ResolvedGeometry {
+        /// The decoder PRT has picked for the URI, if any.
+        pub decoder_id: Option<String>,
+    }
+
+    impl InitialShapeBuilder {
+        /// Decodes the geometry at `uri` (e.g. an OBJ file) with the PRT decoders and sets it as
+        /// the geometry of this builder (vertex coordinates, indices, face counts and holes).
+        pub fn resolve_geometry(&mut self,
+                                uri: &KeyOrUri,
+                                resolve_map: Option<&ResolveMap>,
+                                cache: Option<&Cache>) -> Result<ResolvedGeometry, PrtError>
+        {
+            #[derive(Default)]
+            struct DecodedGeometry {
+                vertex_coords: Vec<f64>,
+                indices: Vec<u32>,
+                face_counts: Vec<u32>,
+                holes: Vec<u32>,
+                decoder_id: Option<String>,
+            }
+
+            extern "C" fn set_geometry(context: *mut DecodedGeometry,
+                                       vertex_coords: *const f64, vertex_coords_count: libc::size_t,
+                                       indices: *const u32, indices_count: libc::size_t,
+                                       face_counts: *const u32, face_counts_count: libc::size_t,
+                                       holes: *const u32, holes_count: libc::size_t,
+                                       decoder_id: *const ffi::c_char) {
+                unsafe {
+                    let decoded = &mut *context;
+                    decoded.vertex_coords = crate::helpers::from_raw_parts_to_vec(vertex_coords, vertex_coords_count);
+                    decoded.indices = crate::helpers::from_raw_parts_to_vec(indices, indices_count);
+                    decoded.face_counts = crate::helpers::from_raw_parts_to_vec(face_counts, face_counts_count);
+                    decoded.holes = crate::helpers::from_raw_parts_to_vec(holes, holes_count);
+                    if !decoder_id.is_null() {
+                        decoded.decoder_id = Some(crate::helpers::from_char_ptr_to_string(decoder_id));
+                    }
+                }
+            }
+
+            let uri_cstring = uri.ffi_to_cstring().map_err(|reason| PrtError {
+                message: format!("Geometry URI '{}' {}", uri, reason),
+                status: Some(Status::STATUS_INVALID_URI),
+            })?;
+
+            let mut decoded = DecodedGeometry::default();
+            let mut binding = prt_ffi::AbstractGeometryBinding {
+                set_geometry,
+                context: ptr::addr_of_mut!(decoded),
+            };
+            let status = unsafe {
+                prt_ffi::ffi_resolve_geometry(uri_cstring.as_ptr(),
+                                              resolve_map.map_or(ptr::null(), |rm| rm.handle),
+                                              cache.map_or(ptr::null_mut(), |c| c.handle),
+                                              ptr::addr_of_mut!(binding) as *mut ffi::c_void)
+            };
+            if status != Status::STATUS_OK {
+                let decoder = decoded.decoder_id.as_deref().unwrap_or("no matching decoder");
+                return Err(PrtError {
+                    message: format!("Could not decode geometry '{}' ({}): {}",
+                                     uri, decoder, get_status_description(status)),
+                    status: Some(status),
+                });
+            }
+
+            self.vertex_coords = Some(decoded.vertex_coords);
+            self.indices = Some(decoded.indices);
+            self.face_counts = Some(decoded.face_counts);
+            self.holes = Some(prt_ffi::unflatten_holes(&decoded.holes));
+            Ok(ResolvedGeometry { decoder_id: decoded.decoder_id })
+        }
+    }
+
+    /// Typed counterpart of `prt::OcclusionSet::Handle`, one per initial shape.
+    #[repr(transparent)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct OcclusionHandle(u64);
+
+    /// Owns a native `prt::OcclusionSet` which collects the occluders of a batch of initial shapes.
+    pub struct OcclusionSet {
+        handle: *mut prt_ffi::OcclusionSet,
+    }
+
+    unsafe impl Send for OcclusionSet {}
+
+    impl OcclusionSet {
+        pub fn new() -> Result<OcclusionSet, PrtError> {
+            let handle = unsafe { prt_ffi::ffi_occlusion_set_create() };
+            if handle.is_null() {
+                return Err(PrtError {
+                    message: "Could not create occlusion set".to_string(),
+                    status: None,
+                });
+            }
+            Ok(OcclusionSet { handle })
+        }
+    }
+
+    impl Drop for OcclusionSet {
+        fn drop(&mut self) {
+            unsafe {
+                prt_ffi::ffi_occlusion_set_destroy(self.handle);
+            }
+        }
+    }
+
+    /// Typed generate options, see the documentation of `prt::generate` for the meaning of the individual keys.
+    /// Options which are not set are left to the PRT defaults.
+    #[derive(Clone, Default, Builder, Debug, PartialEq)]
+    #[builder(default, pattern = "owned")]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+    pub struct GenerateOptions {
+        /// Maximum number of threads PRT uses to generate the initial shapes (defaults to the number of cores).
+        #[builder(setter(strip_option))]
+        number_worker_threads: Option<i32>,
+
+        /// Keep the generated shape trees in the cache, speeds up re-generation with different encoders.
+        #[builder(setter(strip_option))]
+        cache_shape_tree: Option<bool>,
+
+        /// Occlusion queries (e.g. `inside()`) only consider the occluders of the initial shapes within this
+        /// distance, speeds up generating with large occlusion sets.
+        #[builder(setter(strip_option))]
+        occlusion_neighborhood_size: Option<f64>,
+
+        /// Any other generate option by its PRT key, wins over a typed option with the same key.
+        extra_options: collections::HashMap<String, PrimitiveType>,
+    }
+
+    impl GenerateOptions {
+        /// The options by their PRT key, the extra options win over the typed options with the same key (like
+        /// the `extra_options` of the typed encoder options).
+        fn to_options(&self) -> collections::HashMap<String, PrimitiveType> {
+            let typed_options = [
+                ("numberWorkerThreads", self.number_worker_threads.map(PrimitiveType::Int)),
+                ("cacheShapeTree", self.cache_shape_tree.map(PrimitiveType::Bool)),
+                ("occlusionNeighborhoodSize", self.occlusion_neighborhood_size.map(PrimitiveType::Float)),
+            ];
+            let mut options: collections::HashMap<String, PrimitiveType> = typed_options.into_iter()
+                .filter_map(|(k, v)| v.map(|v| (k.to_string(), v)))
+                .collect();
+            options.extend(self.extra_options.iter().map(|(k, v)| (k.clone(), v.clone())));
+            options
+        }
+
+        fn to_attribute_map(&self) -> Result<prt_ffi::AttributeMapOwner, PrtError> {
+            let options = self.to_options();
+            prt_ffi::AttributeMapOwner::from_entries(options.iter().map(|(k, v)| (k.as_str(), v)))
+        }
+    }
 
-    impl Callbacks for FileCallbacks {}
+    /// First pass of the occlusion workflow: generates the occluders of all initial shapes into `occlusion_set`
+    /// and returns one handle per initial shape (in the same order). Pass the handles together with the same
+    /// occlusion set to [`generate`] to evaluate occlusion queries like `inside()` against the neighbours.
+    /// A `cache` shared with the second pass avoids resolving and parsing the rule packages twice.
+    pub fn generate_occluders<C>(initial_shapes: &Vec<Box<InitialShape>>,
+                                 encoders: &Vec<String>,
+                                 encoder_options: &Vec<EncoderOptions>,
+                                 callbacks: &mut C,
+                                 occlusion_set: &mut OcclusionSet,
+                                 generate_options: Option<&GenerateOptions>,
+                                 cache: Option<&Cache>) -> Result<Vec<OcclusionHandle>, PrtError>
+        where C: Callbacks + Send
+    {
+        if encoders.len() != encoder_options.len() {
+            return Err(PrtError {
+                message: get_status_description(Status::STATUS_ARGUMENTS_MISMATCH),
+                status: Some(Status::STATUS_ARGUMENTS_MISMATCH),
+            });
+        }
 
+        let mut occlusion_handles = vec![OcclusionHandle::default(); initial_shapes.len()];
+        let occlusion_handles_ptr = occlusion_handles.as_mut_ptr() as *mut u64;
+        let inputs = GenerateInputs {
+            initial_shapes,
+            encoders,
+            encoder_options,
+            generate_options,
+            cache,
+            ..GenerateInputs::default()
+        };
+        let status = with_ffi_generate_args(&inputs, callbacks, |args| {
+            let cache: *mut prt_ffi::Cache = inputs.cache.map_or(ptr::null_mut(), |c| c.handle);
+            unsafe {
+                prt_ffi::ffi_generate_occluders(args.initial_shapes.as_ptr(),
+                                                args.initial_shapes.len(),
+                                                occlusion_handles_ptr,
+                                                args.encoders.as_ptr(), args.encoders.len(),
+                                                args.encoder_options.as_ptr(),
+                                                args.callbacks,
+                                                cache,
+                                                occlusion_set.handle,
+                                                args.generate_options)
+            }
+        })?;
+
+        if status != Status::STATUS_OK {
+            return Err(PrtError {
+                message: format!("Error while generating occluders: {}", get_status_description(status)),
+                status: Some(status),
+            });
+        }
+        Ok(occlusion_handles)
+    }
+
+    /// Fails with the reason if the inputs cannot be passed to PRT (e.g. the shape index and field of an initial
+    /// shape with an undefined rule file or a NUL byte in the name) or with the status of PRT if generating does
+    /// not succeed, e.g. `STATUS_CANCELED`.
     pub fn generate<C>(initial_shapes: &Vec<Box<InitialShape>>,
+                       occlusion_handles: Option<&Vec<OcclusionHandle>>,
                        encoders: &Vec<String>,
                        encoder_options: &Vec<EncoderOptions>,
-                       callbacks: &mut Box<C>) -> Status // todo: consistent error handling
-        where C: Callbacks
+                       callbacks: &mut C,
+                       occlusion_set: Option<&OcclusionSet>,
+                       generate_options: Option<&GenerateOptions>) -> Result<(), PrtError>
+        where C: Callbacks + Send
     {
-        if encoders.len() != encoder_options.len() {
-            return Status::STATUS_ARGUMENTS_MISMATCH;
+        let inputs = GenerateInputs {
+            initial_shapes,
+            occlusion_handles: occlusion_handles.map(Vec::as_slice),
+            encoders,
+            encoder_options,
+            occlusion_set,
+            generate_options,
+            cache: None,
+        };
+        try_generate(&inputs, callbacks)
+    }
+
+    /// The arguments of [`try_generate`], see [`generate`] for their meaning. Unset fields default to empty
+    /// (respectively none).
+    #[derive(Clone, Copy, Default)]
+    pub struct GenerateInputs<'a> {
+        pub initial_shapes: &'a [Box<InitialShape>],
+        /// Requires the occlusion set the handles have been generated into, see [`generate_occluders`].
+        pub occlusion_handles: Option<&'a [OcclusionHandle]>,
+        pub encoders: &'a [String],
+        /// One entry per encoder.
+        pub encoder_options: &'a [EncoderOptions],
+        pub occlusion_set: Option<&'a OcclusionSet>,
+        pub generate_options: Option<&'a GenerateOptions>,
+        /// Shared between generate calls to avoid resolving and parsing the rule packages again.
+        pub cache: Option<&'a Cache>,
+    }
+
+    /// Like [`generate`], with the arguments in a struct, e.g. to pass a shared [`Cache`].
+    pub fn try_generate<C>(inputs: &GenerateInputs, callbacks: &mut C) -> Result<(), PrtError>
+        where C: Callbacks + Send
+    {
+        let status = generate_status(inputs, callbacks)?;
+        if status != Status::STATUS_OK {
+            return Err(PrtError {
+                message: format!("Error while generating: {}", get_status_description(status)),
+                status: Some(status),
+            });
+        }
+        Ok(())
+    }
+
+    // the status of the generate call, invalid inputs are returned as an error with a message
+    fn generate_status<C>(inputs: &GenerateInputs, callbacks: &mut C) -> Result<Status, PrtError>
+        where C: Callbacks + Send
+    {
+        if inputs.encoders.len() != inputs.encoder_options.len() {
+            return Ok(Status::STATUS_ARGUMENTS_MISMATCH);
+        }
+
+        // occlusion handles are only meaningful together with the set they have been generated into
+        let occlusion_handles_ptr: *const u64 = match (inputs.occlusion_handles, inputs.occlusion_set) {
+            (Some(handles), Some(_)) if handles.len() == inputs.initial_shapes.len() => {
+                handles.as_ptr() as *const u64
+            }
+            (None, None) => ptr::null(),
+            _ => return Ok(Status::STATUS_ARGUMENTS_MISMATCH),
+        };
+        let occl_set: *const prt_ffi::OcclusionSet = match inputs.occlusion_set {
+            Some(set) => set.handle,
+            None => ptr::null(),
+        };
+
+        with_ffi_generate_args(inputs, callbacks, |args| {
+            let cache: *mut prt_ffi::Cache = inputs.cache.map_or(ptr::null_mut(), |c| c.handle);
+            unsafe {
+                prt_ffi::ffi_generate(args.initial_shapes.as_ptr(),
+                                      args.initial_shapes.len(),
+                                      occlusion_handles_ptr,
+                                      args.encoders.as_ptr(), args.encoders.len(),
+                                      args.encoder_options.as_ptr(),
+                                      args.callbacks,
+                                      cache,
+                                      occl_set,
+                                      args.generate_options)
+            }
+        })
+    }
+
+    fn generate_collecting<C>(initial_shapes: &[Box<InitialShape>],
+                              encoders: Vec<String>,
+                              callbacks: &mut C,
+                              generate_options: Option<&GenerateOptions>)
+                              -> Result<(Vec<Reports>, Vec<AttributeValues>), PrtError>
+        where C: Callbacks + Send
+    {
+        let encoder_options: Vec<EncoderOptions> = encoders.iter().map(|_| EncoderOptions::new()).collect();
+
+        let mut collector = ShapeValueCollector {
+            callbacks,
+            reports: initial_shapes.iter().map(|_| Reports::new()).collect(),
+            attributes: initial_shapes.iter().map(|_| AttributeValues::new()).collect(),
+        };
+        let inputs = GenerateInputs {
+            initial_shapes,
+            encoders: &encoders,
+            encoder_options: &encoder_options,
+            generate_options,
+            ..GenerateInputs::default()
+        };
+        try_generate(&inputs, &mut collector)?;
+        Ok((collector.reports, collector.attributes))
+    }
+
+    /// Generates the initial shapes with the CGA report encoder only and returns the reports per initial shape.
+    /// Nothing is written to disk. If `capture_geometry` is set, the meshes are additionally passed to
+    /// [`Callbacks::on_mesh`], otherwise all geometry encoding is skipped.
+    pub fn generate_reports<C>(initial_shapes: &[Box<InitialShape>],
+                               callbacks: &mut C,
+                               capture_geometry: bool,
+                               generate_options: Option<&GenerateOptions>) -> Result<Vec<Reports>, PrtError>
+        where C: Callbacks + Send
+    {
+        let mut encoders = vec![encoders::CGA_REPORT_ENCODER_ID.to_string()];
+        if capture_geometry {
+            encoders.push(encoders::GEOMETRY_CAPTURE_ENCODER_ID.to_string());
+        }
+        generate_collecting(initial_shapes, encoders, callbacks, generate_options)
+            .map(|(reports, _)| reports)
+    }
+
+    /// Evaluates the rule attributes of the initial shapes and returns their final values per initial shape.
+    /// CGA 2D arrays are returned as the `*Array2D` variants of [`PrimitiveType`].
+    pub fn generate_attributes<C>(initial_shapes: &[Box<InitialShape>],
+                                  callbacks: &mut C,
+                                  generate_options: Option<&GenerateOptions>)
+                                  -> Result<Vec<AttributeValues>, PrtError>
+        where C: Callbacks + Send
+    {
+        let encoders = vec![encoders::ATTRIBUTE_EVAL_ENCODER_ID.to_string()];
+        generate_collecting(initial_shapes, encoders, callbacks, generate_options)
+            .map(|(_, attributes)| attributes)
+    }
+
+    /// The inputs of a [`generate`] call without occlusion, e.g. to store a job as JSON with the `serde` feature.
+    #[derive(Debug, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct GenerateJob {
+        pub initial_shapes: Vec<Box<InitialShape>>,
+        pub encoders: Vec<String>,
+        /// One entry per encoder.
+        pub encoder_options: Vec<EncoderOptions>,
+        #[cfg_attr(feature = "serde", serde(default))]
+        pub generate_options: Option<GenerateOptions>,
+    }
+
+    impl GenerateJob {
+        pub fn generate<C>(&self, callbacks: &mut C) -> Result<(), PrtError>
+            where C: Callbacks + Send
+        {
+            generate(&self.initial_shapes, None, &self.encoders, &self.encoder_options, callbacks, None,
+                     self.generate_options.as_ref())
         }
+    }
+
+    // the FFI representation of the arguments shared by generate and generate_occluders
+    struct FfiGenerateArgs {
+        initial_shapes: Vec<*const prt_ffi::InitialShapeWrapper>,
+        encoders: Vec<*const libc::wchar_t>,
+        encoder_options: Vec<*const prt_ffi::AttributeMap>,
+        callbacks: *mut ffi::c_void,
+        generate_options: *const prt_ffi::AttributeMap,
+    }
 
+    // prepares the FFI arguments and keeps their owners alive while the native call runs
+    fn with_ffi_generate_args<C, F>(inputs: &GenerateInputs, callbacks: &mut C, native_call: F)
+                                    -> Result<Status, PrtError>
+        where C: Callbacks + Send,
+              F: FnOnce(&FfiGenerateArgs) -> Status
+    {
         // wrap the initial shapes into an adaptor to have a mutable place
-        // where we can hold any owners of C pointers
-        let mut initial_shape_adaptors: Vec<prt_ffi::InitialShapeAdaptor> = initial_shapes.iter()
-            .map(|x| prt_ffi::InitialShapeAdaptor::adapt(x))
-            .collect();
+        // where we can hold any owners of C pointers, this also validates them
+        let mut initial_shape_adaptors = inputs.initial_shapes.iter().enumerate()
+            .map(|(index, x)| prt_ffi::InitialShapeAdaptor::adapt(index, x))
+            .collect::<Result<Vec<prt_ffi::InitialShapeAdaptor>, PrtError>>()?;
 
         let initial_shape_wrappers: Vec<prt_ffi::InitialShapeWrapper> = initial_shape_adaptors.iter_mut()
             .map(|x: &mut prt_ffi::InitialShapeAdaptor| x.get_ffi_wrapper())
@@ -157,39 +1065,115 @@ pub mod prt {
             .map(|x| &*x as *const prt_ffi::InitialShapeWrapper)
             .collect();
 
-        let occlusion_handles: *const u64 = ptr::null();
-
         // TODO: probably better to stay UTF-8 on the rust side and convert in the native wrapper
-        let encoders_wchar_vec: Vec<Vec<libc::wchar_t>> = encoders.iter()
+        let encoders_wchar_vec: Vec<Vec<libc::wchar_t>> = inputs.encoders.iter()
             .map(|x| crate::helpers::from_string_to_wchar_vec(x.as_str()))
             .collect();
         let encoders_ptr_vec: Vec<*const libc::wchar_t> = encoders_wchar_vec.iter().map(|x| x.as_ptr()).collect();
 
-        let encoder_options_ptr_vec: *const prt_ffi::AttributeMap = ptr::null();
+        let encoder_options_owners = inputs.encoder_options.iter()
+            .map(|options| prt_ffi::AttributeMapOwner::from_entries(options.iter().map(|(k, v)| (k.as_str(), v))))
+            .collect::<Result<Vec<prt_ffi::AttributeMapOwner>, PrtError>>()?;
 
-        let callbacks_context: *mut C = callbacks.as_mut();
-        let callbacks_binding: Box<prt_ffi::AbstractCallbacksBinding<C>>
-            = Box::new(prt_ffi::AbstractCallbacksBinding { context: callbacks_context });
-        let callbacks_binding_ptr
-            = Box::into_raw(callbacks_binding) as *mut ffi::c_void;
+        let generate_options_owner = inputs.generate_options.map(GenerateOptions::to_attribute_map).transpose()?;
 
-        let cache: *mut prt_ffi::Cache = ptr::null_mut();
-        let occl_set: *const prt_ffi::OcclusionSet = ptr::null();
-        let generate_options: *const prt_ffi::AttributeMap = ptr::null();
+        extern "C" fn is_canceled<C>(context: *mut C) -> bool
+            where C: Callbacks
+        {
+            unsafe {
+                let callbacks_ref: &C = &*context;
+                callbacks_ref.is_canceled()
+            }
+        }
 
-        unsafe {
-            let status = prt_ffi::ffi_generate(initial_shape_wrapper_ptr_vec.as_ptr(),
-                                               initial_shape_wrapper_ptr_vec.len(),
-                                               occlusion_handles,
-                                               encoders_ptr_vec.as_ptr(), encoders_ptr_vec.len(),
-                                               encoder_options_ptr_vec,
-                                               callbacks_binding_ptr,
-                                               cache,
-                                               occl_set,
-                                               generate_options);
+        extern "C" fn on_progress<C>(context: *mut C, initial_shape_index: libc::size_t,
+                                     initial_shape_count: libc::size_t, state: prt_ffi::ProgressState,
+                                     status: Status, elapsed_seconds: f64)
+            where C: Callbacks
+        {
+            let progress = match state {
+                prt_ffi::ProgressState::Started => ShapeProgress::Started,
+                prt_ffi::ProgressState::Finished => ShapeProgress::Finished,
+                prt_ffi::ProgressState::Failed => ShapeProgress::Failed(status),
+            };
+            unsafe {
+                let callbacks_ref: &mut C = &mut *context;
+                callbacks_ref.on_progress(ProgressEvent {
+                    initial_shape_index,
+                    initial_shape_count,
+                    progress,
+                    elapsed: time::Duration::from_secs_f64(elapsed_seconds),
+                });
+            }
+        }
 
-            return status;
+        extern "C" fn on_mesh<C>(context: *mut C, initial_shape_index: libc::size_t,
+                                 mesh: *const prt_ffi::CapturedMeshWrapper)
+            where C: Callbacks
+        {
+            unsafe {
+                let callbacks_ref: &mut C = &mut *context;
+                callbacks_ref.on_mesh(initial_shape_index, (*mesh).to_captured_mesh());
+            }
         }
+
+        extern "C" fn on_report<C>(context: *mut C, initial_shape_index: libc::size_t, key: *const ffi::c_char,
+                                   value: *const prt_ffi::PrimitiveWrapper)
+            where C: Callbacks
+        {
+            unsafe {
+                let callbacks_ref: &mut C = &mut *context;
+                let key = crate::helpers::from_char_ptr_to_string(key);
+                callbacks_ref.on_report(initial_shape_index, &key, (*value).to_primitive());
+            }
+        }
+
+        extern "C" fn on_attribute<C>(context: *mut C, initial_shape_index: libc::size_t, key: *const ffi::c_char,
+                                      value: *const prt_ffi::PrimitiveWrapper)
+            where C: Callbacks
+        {
+            unsafe {
+                let callbacks_ref: &mut C = &mut *context;
+                let key = crate::helpers::from_char_ptr_to_string(key);
+                callbacks_ref.on_attribute(initial_shape_index, &key, (*value).to_primitive());
+            }
+        }
+
+        if callbacks.is_canceled() {
+            return Ok(Status::STATUS_CANCELED);
+        }
+
+        let output_directory_owner = callbacks.output_directory()
+            .map(|dir| dir.to_str().and_then(|dir| ffi::CString::new(dir).ok()).ok_or_else(|| PrtError {
+                message: format!("Invalid output directory: {}", dir.display()),
+                status: Some(Status::STATUS_ILLEGAL_VALUE),
+            }))
+            .transpose()?;
+
+        let callbacks_context: *mut C = callbacks;
+        let callbacks_binding: Box<prt_ffi::AbstractCallbacksBinding<C>>
+            = Box::new(prt_ffi::AbstractCallbacksBinding {
+                context: callbacks_context,
+                output_directory: output_directory_owner.as_ref().map_or(ptr::null(), |dir| dir.as_ptr()),
+                is_canceled,
+                on_progress,
+                on_mesh,
+                on_report,
+                on_attribute,
+            });
+        let callbacks_binding_ptr = Box::into_raw(callbacks_binding);
+
+        let status = native_call(&FfiGenerateArgs {
+            initial_shapes: initial_shape_wrapper_ptr_vec,
+            encoders: encoders_ptr_vec,
+            encoder_options: encoder_options_owners.iter().map(|o| o.as_ptr()).collect(),
+            callbacks: callbacks_binding_ptr as *mut ffi::c_void,
+            generate_options: generate_options_owner.as_ref().map_or(ptr::null(), |o| o.as_ptr()),
+        });
+
+        // the native side only borrows the binding for the duration of the call
+        drop(unsafe { Box::from_raw(callbacks_binding_ptr) });
+        Ok(status)
     }
 
     #[allow(non_camel_case_types)]
@@ -218,7 +1202,7 @@ pub mod prt {
         }
     }
 
-    pub fn add_log_handler<T>(log_handler: &mut Box<T>) where T: LogHandler {
+    pub fn add_log_handler<T>(log_handler: &mut Box<T>) where T: LogHandler + Send {
         extern "C" fn handle_log_event<T>(context: *mut T, cmsg: *const ffi::c_char)
             where T: LogHandler
         {
@@ -235,26 +1219,19 @@ pub mod prt {
             context,
         });
 
-        let binding_ptr: *mut ffi::c_void = Box::into_raw(binding) as *mut ffi::c_void;
-        unsafe {
-            prt_ffi::ffi_add_log_handler(binding_ptr);
+        // the native side only borrows the binding, it is handed back by ffi_remove_log_handler
+        let binding_ptr = Box::into_raw(binding);
+        let added = unsafe { prt_ffi::ffi_add_log_handler(binding_ptr as *mut ffi::c_void) };
+        if !added {
+            drop(unsafe { Box::from_raw(binding_ptr) });
         }
     }
 
     pub fn remove_log_handler<T>(log_handler: &mut Box<T>) where T: LogHandler {
-        unsafe extern "C" fn handle_log_event<T>(_context: *mut T, _cmsg: *const ffi::c_char)
-            where T: LogHandler
-        {}
-
         let context = log_handler.as_mut() as *mut T;
-        let binding: Box<prt_ffi::AbstractLogHandlerBinding<T>> = Box::new(prt_ffi::AbstractLogHandlerBinding {
-            handle_log_event,
-            context,
-        });
-
-        let binding_ptr: *mut ffi::c_void = Box::into_raw(binding) as *mut ffi::c_void;
-        unsafe {
-            prt_ffi::ffi_remove_log_handler(binding_ptr);
+        let binding_ptr = unsafe { prt_ffi::ffi_remove_log_handler(context as *mut ffi::c_void) };
+        if !binding_ptr.is_null() {
+            drop(unsafe { Box::from_raw(binding_ptr as *mut prt_ffi::AbstractLogHandlerBinding<T>) });
         }
     }
 
@@ -267,6 +1244,7 @@ pub mod prt {
 
     #[allow(non_camel_case_types)]
     #[allow(dead_code)]
+    #[derive(Clone, Copy)]
     #[derive(PartialEq)]
     #[derive(Debug)]
     #[repr(C)]
@@ -354,42 +1332,328 @@ pub mod prt {
         pub cga_version_minor: i32,
         pub cga_version_string: String,
 
-        pub cgac_version_major: i32,
-        pub cgac_version_minor: i32,
-        pub cgac_version_string: String,
+        pub cgac_version_major: i32,
+        pub cgac_version_minor: i32,
+        pub cgac_version_string: String,
+    }
+
+    pub fn get_version() -> Result<PrtVersion, PrtError> {
+        unsafe {
+            let version_ptr = prt_ffi::ffi_get_version();
+            if version_ptr == ptr::null() {
+                return Err(PrtError {
+                    message: "Could not get PRT version info".to_string(),
+                    status: None,
+                });
+            }
+            let version_ref = &*version_ptr;
+            let ver = PrtVersion {
+                version_major: version_ref.version_major,
+                version_minor: version_ref.version_minor,
+                version_build: version_ref.version_build,
+                version_string: crate::helpers::from_char_ptr_to_string(version_ref.version),
+                name: crate::helpers::from_char_ptr_to_string(version_ref.name),
+                full_name: crate::helpers::from_char_ptr_to_string(version_ref.full_name),
+                build_config: crate::helpers::from_char_ptr_to_string(version_ref.build_config),
+                build_os: crate::helpers::from_char_ptr_to_string(version_ref.build_os),
+                build_arch: crate::helpers::from_char_ptr_to_string(version_ref.build_arch),
+                build_tc: crate::helpers::from_char_ptr_to_string(version_ref.build_tc),
+                build_date: crate::helpers::from_char_ptr_to_string(version_ref.build_date),
+                cga_version_major: version_ref.cga_version_major,
+                cga_version_minor: version_ref.cga_version_minor,
+                cga_version_string: crate::helpers::from_char_ptr_to_string(version_ref.cga_version),
+                cgac_version_major: version_ref.cgac_version_major,
+                cgac_version_minor: version_ref.cgac_version_minor,
+                cgac_version_string: crate::helpers::from_char_ptr_to_string(version_ref.cgac_version),
+            };
+            Ok(ver)
+        }
+    }
+
+    #[allow(non_camel_case_types)]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum ContentType {
+        CT_UNDEFINED,
+        CT_GEOMETRY,
+        CT_MATERIAL,
+        CT_TEXTURE,
+        CT_SHADER,
+        CT_CGAERROR,
+        CT_CGAPRINT,
+        CT_CGAREPORT,
+        CT_ATTRIBUTE,
+        CT_INITIALSHAPE,
+        CT_RESOLVEMAP,
+    }
+
+    impl ContentType {
+        fn from_ffi(content_type: i32) -> ContentType {
+            match content_type {
+                1 => ContentType::CT_GEOMETRY,
+                2 => ContentType::CT_MATERIAL,
+                3 => ContentType::CT_TEXTURE,
+                4 => ContentType::CT_SHADER,
+                5 => ContentType::CT_CGAERROR,
+                6 => ContentType::CT_CGAPRINT,
+                7 => ContentType::CT_CGAREPORT,
+                8 => ContentType::CT_ATTRIBUTE,
+                9 => ContentType::CT_INITIALSHAPE,
+                10 => ContentType::CT_RESOLVEMAP,
+                _ => ContentType::CT_UNDEFINED,
+            }
+        }
+    }
+
+    /// Description of an installed encoder or decoder, see `prt::EncoderInfo` and `prt::DecoderInfo`.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct CodecInfo {
+        pub id: String,
+        pub name: String,
+        pub description: String,
+        /// File extensions without the leading dot, e.g. `obj`.
+        pub extensions: Vec<String>,
+        /// The content types the codec handles, empty if PRT reports none.
+        pub content_types: Vec<ContentType>,
+    }
+
+    /// Lists the encoders of all loaded extensions, including the ones from extra plugin paths.
+    pub fn list_encoders() -> Result<Vec<CodecInfo>, PrtError> {
+        get_codec_infos(prt_ffi::CodecKind::Encoder, None)
+    }
+
+    /// Lists the decoders of all loaded extensions, including the ones from extra plugin paths.
+    pub fn list_decoders() -> Result<Vec<CodecInfo>, PrtError> {
+        get_codec_infos(prt_ffi::CodecKind::Decoder, None)
+    }
+
+    pub fn get_encoder_info(encoder_id: &str) -> Result<CodecInfo, PrtError> {
+        get_codec_infos(prt_ffi::CodecKind::Encoder, Some(encoder_id)).map(|mut infos| infos.remove(0))
+    }
+
+    pub fn get_decoder_info(decoder_id: &str) -> Result<CodecInfo, PrtError> {
+        get_codec_infos(prt_ffi::CodecKind::Decoder, Some(decoder_id)).map(|mut infos| infos.remove(0))
+    }
+
+    fn get_codec_infos(kind: prt_ffi::CodecKind, codec_id: Option<&str>) -> Result<Vec<CodecInfo>, PrtError> {
+        extern "C" fn add_codec_info(context: *mut Vec<CodecInfo>, info: *const prt_ffi::CodecInfoWrapper) {
+            unsafe {
+                let infos = &mut *context;
+                let info = &*info;
+                infos.push(CodecInfo {
+                    id: crate::helpers::from_char_ptr_to_string(info.id),
+                    name: crate::helpers::from_char_ptr_to_string(info.name),
+                    description: crate::helpers::from_char_ptr_to_string(info.description),
+                    extensions: crate::helpers::from_char_ptr_to_string(info.extensions)
+                        .split(';')
+                        .map(|e| e.trim().trim_start_matches(['*', '.']).to_string())
+                        .filter(|e| !e.is_empty())
+                        .collect(),
+                    content_types: crate::helpers::from_raw_parts_to_vec(info.content_types,
+                                                                         info.content_type_count)
+                        .into_iter()
+                        .map(ContentType::from_ffi)
+                        .collect(),
+                });
+            }
+        }
+
+        let codec_id_cstring = match codec_id {
+            Some(id) => Some(ffi::CString::new(id).map_err(|_| PrtError {
+                message: format!("Codec id '{}' contains a NUL byte", id),
+                status: Some(Status::STATUS_ILLEGAL_VALUE),
+            })?),
+            None => None,
+        };
+
+        let mut infos: Vec<CodecInfo> = Vec::new();
+        let mut binding = prt_ffi::AbstractCodecInfoBinding {
+            add_codec_info,
+            context: ptr::addr_of_mut!(infos),
+        };
+        let status = unsafe {
+            prt_ffi::ffi_get_codec_infos(kind,
+                                         codec_id_cstring.as_ref().map_or(ptr::null(), |id| id.as_ptr()),
+                                         ptr::addr_of_mut!(binding) as *mut ffi::c_void)
+        };
+        if status != Status::STATUS_OK || (codec_id.is_some() && infos.is_empty()) {
+            let status = if status == Status::STATUS_OK { Status::STATUS_KEY_NOT_FOUND } else { status };
+            return Err(PrtError {
+                message: format!("Could not get codec info for '{}': {}",
+                                 codec_id.unwrap_or("*"), get_status_description(status)),
+                status: Some(status),
+            });
+        }
+        Ok(infos)
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum OptionState {
+        Enabled,
+        Disabled,
+        Hidden,
+        Unknown(String),
+    }
+
+    impl OptionState {
+        fn from_primitive(state: Option<&PrimitiveType>) -> OptionState {
+            match state {
+                Some(PrimitiveType::String(s)) => match s.to_ascii_uppercase().as_str() {
+                    "ENABLED" => OptionState::Enabled,
+                    "DISABLED" => OptionState::Disabled,
+                    "HIDDEN" => OptionState::Hidden,
+                    _ => OptionState::Unknown(s.clone()),
+                },
+                Some(other) => OptionState::Unknown(format!("{:?}", other)),
+                None => OptionState::Enabled,
+            }
+        }
+    }
+
+    /// An annotation of an encoder option, e.g. `@Range(min=0, max=1)`.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct OptionAnnotation {
+        /// As reported by PRT, e.g. `@Range`.
+        pub name: String,
+        /// The arguments in order with their keys, the key is empty for positional arguments.
+        pub arguments: Vec<(String, PrimitiveType)>,
+    }
+
+    impl OptionAnnotation {
+        fn is(&self, name: &str) -> bool {
+            self.name.trim_start_matches('@').eq_ignore_ascii_case(name)
+        }
+
+        fn argument(&self, key: &str, position: usize) -> Option<&PrimitiveType> {
+            self.arguments.iter().find(|(k, _)| k == key).or_else(|| self.arguments.get(position)).map(|(_, v)| v)
+        }
+    }
+
+    /// Describes one option of an encoder, the variant of the default value is the value type of the option.
+    #[derive(Debug, PartialEq)]
+    pub struct EncoderOptionInfo {
+        pub key: String,
+        pub default_value: PrimitiveType,
+        pub state: OptionState,
+        /// The (min, max) of the `@Range` annotation.
+        pub range: Option<(f64, f64)>,
+        /// The values of the `@Enum` annotation, empty if the option has none.
+        pub enum_values: Vec<PrimitiveType>,
+        /// The text of the `@Description` annotation.
+        pub description: Option<String>,
+        /// All annotations of the option, including the ones above.
+        pub annotations: Vec<OptionAnnotation>,
+    }
+
+    impl EncoderOptionInfo {
+        fn new(key: String, default_value: PrimitiveType, state: OptionState, annotations: Vec<OptionAnnotation>)
+               -> EncoderOptionInfo {
+            let number = |value: Option<&PrimitiveType>| match value {
+                Some(PrimitiveType::Float(f)) => Some(*f),
+                Some(PrimitiveType::Int(i)) => Some(*i as f64),
+                _ => None,
+            };
+            let range = annotations.iter().find(|a| a.is("Range"))
+                .and_then(|a| Some((number(a.argument("min", 0))?, number(a.argument("max", 1))?)));
+            let enum_values = annotations.iter().find(|a| a.is("Enum"))
+                .map(|a| a.arguments.iter().map(|(_, v)| v.clone()).collect())
+                .unwrap_or_default();
+            let description = annotations.iter().find(|a| a.is("Description"))
+                .and_then(|a| match a.argument("description", 0) {
+                    Some(PrimitiveType::String(s)) => Some(s.clone()),
+                    _ => None,
+                });
+            EncoderOptionInfo { key, default_value, state, range, enum_values, description, annotations }
+        }
+    }
+
+    /// Lists the options of an encoder with their default values and states, sorted by key.
+    pub fn get_encoder_options_info(encoder_id: &str) -> Result<Vec<EncoderOptionInfo>, PrtError> {
+        let (defaults, states) = validate_encoder_options_with_states(encoder_id, &EncoderOptions::new())?;
+        let mut annotations = get_encoder_option_annotations(encoder_id)?;
+        let mut infos: Vec<EncoderOptionInfo> = defaults.into_iter()
+            .map(|(key, default_value)| {
+                let state = OptionState::from_primitive(states.get(&key));
+                let option_annotations = annotations.remove(&key).unwrap_or_default();
+                EncoderOptionInfo::new(key, default_value, state, option_annotations)
+            })
+            .collect();
+        infos.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(infos)
     }
 
-    pub fn get_version() -> Result<PrtVersion, PrtError> {
-        unsafe {
-            let version_ptr = prt_ffi::ffi_get_version();
-            if version_ptr == ptr::null() {
-                return Err(PrtError {
-                    message: "Could not get PRT version info".to_string(),
-                    status: None,
+    type OptionAnnotations = collections::HashMap<String, Vec<OptionAnnotation>>;
+
+    fn get_encoder_option_annotations(encoder_id: &str) -> Result<OptionAnnotations, PrtError> {
+        extern "C" fn add_annotation(context: *mut OptionAnnotations, option_key: *const ffi::c_char,
+                                     name: *const ffi::c_char, argument_keys: *const *const ffi::c_char,
+                                     arguments: *const prt_ffi::PrimitiveWrapper, arguments_count: libc::size_t) {
+            use crate::helpers::{from_char_ptr_to_string as to_string, from_raw_parts_to_vec as to_vec};
+            unsafe {
+                let argument_keys = to_vec(argument_keys, arguments_count);
+                let arguments = (0..arguments_count).map(|i| (*arguments.add(i)).to_primitive());
+                (*context).entry(to_string(option_key)).or_default().push(OptionAnnotation {
+                    name: to_string(name),
+                    arguments: argument_keys.into_iter().map(to_string).zip(arguments).collect(),
                 });
             }
-            let version_ref = &*version_ptr;
-            let ver = PrtVersion {
-                version_major: version_ref.version_major,
-                version_minor: version_ref.version_minor,
-                version_build: version_ref.version_build,
-                version_string: crate::helpers::from_char_ptr_to_string(version_ref.version),
-                name: crate::helpers::from_char_ptr_to_string(version_ref.name),
-                full_name: crate::helpers::from_char_ptr_to_string(version_ref.full_name),
-                build_config: crate::helpers::from_char_ptr_to_string(version_ref.build_config),
-                build_os: crate::helpers::from_char_ptr_to_string(version_ref.build_os),
-                build_arch: crate::helpers::from_char_ptr_to_string(version_ref.build_arch),
-                build_tc: crate::helpers::from_char_ptr_to_string(version_ref.build_tc),
-                build_date: crate::helpers::from_char_ptr_to_string(version_ref.build_date),
-                cga_version_major: version_ref.cga_version_major,
-                cga_version_minor: version_ref.cga_version_minor,
-                cga_version_string: crate::helpers::from_char_ptr_to_string(version_ref.cga_version),
-                cgac_version_major: version_ref.cgac_version_major,
-                cgac_version_minor: version_ref.cgac_version_minor,
-                cgac_version_string: crate::helpers::from_char_ptr_to_string(version_ref.cgac_version),
-            };
-            Ok(ver)
         }
+
+        let encoder_id_cstring = ffi::CString::new(encoder_id).map_err(|_| PrtError {
+            message: format!("Encoder id '{}' contains a NUL byte", encoder_id),
+            status: Some(Status::STATUS_ILLEGAL_VALUE),
+        })?;
+        let mut annotations = OptionAnnotations::new();
+        let mut binding = prt_ffi::AbstractOptionAnnotationBinding {
+            add_annotation,
+            context: ptr::addr_of_mut!(annotations),
+        };
+        let status = unsafe {
+            prt_ffi::ffi_get_encoder_option_annotations(encoder_id_cstring.as_ptr(),
+                                                        ptr::addr_of_mut!(binding) as *mut ffi::c_void)
+        };
+        if status != Status::STATUS_OK {
+            return Err(PrtError {
+                message: format!("Could not get the option annotations of encoder '{}': {}",
+                                 encoder_id, get_status_description(status)),
+                status: Some(status),
+            });
+        }
+        Ok(annotations)
+    }
+
+    /// Lets the encoder validate the given options, returns the complete set of options as the encoder
+    /// would use them, i.e. invalid values are replaced by defaults and missing options are added.
+    pub fn validate_encoder_options(encoder_id: &str, options: &EncoderOptions) -> Result<EncoderOptions, PrtError> {
+        validate_encoder_options_with_states(encoder_id, options).map(|(validated, _)| validated)
+    }
+
+    fn validate_encoder_options_with_states(encoder_id: &str, options: &EncoderOptions)
+                                            -> Result<(EncoderOptions, EncoderOptions), PrtError>
+    {
+        let encoder_id_cstring = ffi::CString::new(encoder_id).map_err(|_| PrtError {
+            message: format!("Encoder id '{}' contains a NUL byte", encoder_id),
+            status: Some(Status::STATUS_ILLEGAL_VALUE),
+        })?;
+        let unvalidated = prt_ffi::AttributeMapOwner::from_entries(options.iter().map(|(k, v)| (k.as_str(), v)))?;
+
+        let mut validated = EncoderOptions::new();
+        let mut states = EncoderOptions::new();
+        let mut validated_binding = prt_ffi::AbstractAttributeMapBinding::new(&mut validated);
+        let mut states_binding = prt_ffi::AbstractAttributeMapBinding::new(&mut states);
+        let status = unsafe {
+            prt_ffi::ffi_validate_encoder_options(encoder_id_cstring.as_ptr(),
+                                                  unvalidated.as_ptr(),
+                                                  ptr::addr_of_mut!(validated_binding) as *mut ffi::c_void,
+                                                  ptr::addr_of_mut!(states_binding) as *mut ffi::c_void)
+        };
+        if status != Status::STATUS_OK {
+            return Err(PrtError {
+                message: format!("Could not validate options of encoder '{}': {}",
+                                 encoder_id, get_status_description(status)),
+                status: Some(status),
+            });
+        }
+        Ok((validated, states))
     }
 
     mod prt_ffi {
@@ -420,10 +1684,250 @@ pub mod prt {
         }
 
         #[repr(C)]
-        struct ResolveMap {
+        pub(crate) struct AttributeMapBuilder {
+            dummy: i32,
+        }
+
+        #[link(name = "bindings", kind = "static")]
+        unsafe extern "C" {
+            fn ffi_attribute_map_builder_create() -> *mut AttributeMapBuilder;
+            fn ffi_attribute_map_builder_destroy(amb: *mut AttributeMapBuilder);
+            fn ffi_attribute_map_builder_set_bool(amb: *mut AttributeMapBuilder, key: *const ffi::c_char, value: bool);
+            fn ffi_attribute_map_builder_set_float(amb: *mut AttributeMapBuilder, key: *const ffi::c_char, value: f64);
+            fn ffi_attribute_map_builder_set_int(amb: *mut AttributeMapBuilder, key: *const ffi::c_char, value: i32);
+            fn ffi_attribute_map_builder_set_string(amb: *mut AttributeMapBuilder, key: *const ffi::c_char,
+                                                    value: *const ffi::c_char);
+            // arrays with rows > 1 are CGA 2D arrays
+            fn ffi_attribute_map_builder_set_bool_array(amb: *mut AttributeMapBuilder, key: *const ffi::c_char,
+                                                        values: *const bool, count: libc::size_t,
+                                                        rows: libc::size_t);
+            fn ffi_attribute_map_builder_set_float_array(amb: *mut AttributeMapBuilder, key: *const ffi::c_char,
+                                                         values: *const f64, count: libc::size_t,
+                                                         rows: libc::size_t);
+            fn ffi_attribute_map_builder_set_int_array(amb: *mut AttributeMapBuilder, key: *const ffi::c_char,
+                                                       values: *const i32, count: libc::size_t,
+                                                       rows: libc::size_t);
+            fn ffi_attribute_map_builder_set_string_array(amb: *mut AttributeMapBuilder, key: *const ffi::c_char,
+                                                          values: *const *const ffi::c_char, count: libc::size_t,
+                                                          rows: libc::size_t);
+            fn ffi_attribute_map_builder_create_attribute_map(amb: *mut AttributeMapBuilder) -> *const AttributeMap;
+            fn ffi_attribute_map_destroy(am: *const AttributeMap);
+        }
+
+        fn to_cstring(key: &str, value: &str) -> Result<ffi::CString, crate::prt::PrtError> {
+            ffi::CString::new(value).map_err(|_| crate::prt::PrtError {
+                message: format!("Value of attribute '{}' contains a NUL byte", key),
+                status: Some(crate::prt::Status::STATUS_ILLEGAL_VALUE),
+            })
+        }
+
+        #[repr(C)]
+        pub(crate) struct PrimitiveWrapper {
+            // see cpp/bindings.h
+            primitive_type: i32,
+            bool_value: bool,
+            float_value: f64,
+            int_value: i32,
+            string_value: *const ffi::c_char,
+            bool_array: *const bool,
+            float_array: *const f64,
+            int_array: *const i32,
+            string_array: *const *const ffi::c_char,
+            count: libc::size_t,
+            rows: libc::size_t, // as reported by PRT, 1 for plain arrays and 0 for non-arrays
+        }
+
+        impl PrimitiveWrapper {
+            pub(crate) fn to_primitive(&self) -> crate::prt::PrimitiveType {
+                use crate::prt::{Array2D, PrimitiveType};
+
+                use crate::helpers::from_raw_parts_to_vec as to_vec;
+
+                // see prt::AttributeMap::PrimitiveType
+                let value = unsafe {
+                    match self.primitive_type {
+                        1 => PrimitiveType::String(crate::helpers::from_char_ptr_to_string(self.string_value)),
+                        2 => PrimitiveType::Float(self.float_value),
+                        3 => PrimitiveType::Bool(self.bool_value),
+                        4 => PrimitiveType::Int(self.int_value),
+                        5 => PrimitiveType::StringArray(to_vec(self.string_array, self.count).into_iter()
+                            .map(crate::helpers::from_char_ptr_to_string)
+                            .collect()),
+                        6 => PrimitiveType::FloatArray(to_vec(self.float_array, self.count)),
+                        7 => PrimitiveType::BoolArray(to_vec(self.bool_array, self.count)),
+                        8 => PrimitiveType::IntArray(to_vec(self.int_array, self.count)),
+                        _ => PrimitiveType::Undefined(),
+                    }
+                };
+
+                // PRT cannot tell a plain array from a 2D array with a single row, only more rows make a 2D array
+                let rows = match self.rows {
+                    0 | 1 => return value,
+                    rows => rows,
+                };
+                let invalid = PrimitiveType::Undefined;
+                match value {
+                    PrimitiveType::StringArray(v) =>
+                        Array2D::new(v, rows).map_or_else(invalid, PrimitiveType::StringArray2D),
+                    PrimitiveType::FloatArray(v) =>
+                        Array2D::new(v, rows).map_or_else(invalid, PrimitiveType::FloatArray2D),
+                    PrimitiveType::BoolArray(v) =>
+                        Array2D::new(v, rows).map_or_else(invalid, PrimitiveType::BoolArray2D),
+                    PrimitiveType::IntArray(v) =>
+                        Array2D::new(v, rows).map_or_else(invalid, PrimitiveType::IntArray2D),
+                    other => other,
+                }
+            }
+        }
+
+        /// Receives the entries of a native prt::AttributeMap.
+        #[repr(C)]
+        pub(crate) struct AbstractAttributeMapBinding<T> {
+            pub(crate) add_attribute: extern "C" fn(*mut T, *const ffi::c_char, *const PrimitiveWrapper),
+            pub(crate) context: *mut T,
+        }
+
+        type PrimitiveMap = std::collections::HashMap<String, crate::prt::PrimitiveType>;
+
+        impl AbstractAttributeMapBinding<PrimitiveMap> {
+            pub(crate) fn new(map: &mut PrimitiveMap) -> AbstractAttributeMapBinding<PrimitiveMap> {
+                extern "C" fn add_attribute(context: *mut PrimitiveMap, key: *const ffi::c_char,
+                                            value: *const PrimitiveWrapper) {
+                    unsafe {
+                        let map = &mut *context;
+                        map.insert(crate::helpers::from_char_ptr_to_string(key), (*value).to_primitive());
+                    }
+                }
+
+                AbstractAttributeMapBinding { add_attribute, context: map as *mut PrimitiveMap }
+            }
+        }
+
+        #[link(name = "bindings", kind = "static")]
+        unsafe extern "C" {
+            pub(crate) fn ffi_validate_encoder_options(encoder_id: *const ffi::c_char,
+                                                       unvalidated_options: *const AttributeMap,
+                                                       validated_options_binding: *mut ffi::c_void,
+                                                       option_states_binding: *mut ffi::c_void) -> crate::prt::Status;
+        }
+
+        #[repr(C)]
+        pub(crate) struct AbstractOptionAnnotationBinding<T> {
+            // option key, annotation name, argument keys, arguments, argument count
+            pub(crate) add_annotation: extern "C" fn(*mut T, *const ffi::c_char, *const ffi::c_char,
+                                                     *const *const ffi::c_char, *const PrimitiveWrapper,
+                                                     libc::size_t),
+            pub(crate) context: *mut T,
+        }
+
+        #[link(name = "bindings", kind = "static")]
+        unsafe extern "C" {
+            pub(crate) fn ffi_get_encoder_option_annotations(encoder_id: *const ffi::c_char,
+                                                             annotation_binding: *mut ffi::c_void)
+                                                             -> crate::prt::Status;
+        }
+
+        /// Owns a native prt::AttributeMap created from Rust primitives.
+        pub(crate) struct AttributeMapOwner {
+            handle: *const AttributeMap,
+        }
+
+        impl AttributeMapOwner {
+            pub(crate) fn from_entries<'a, I>(entries: I) -> Result<AttributeMapOwner, crate::prt::PrtError>
+                where I: IntoIterator<Item = (&'a str, &'a crate::prt::PrimitiveType)>
+            {
+                use crate::prt::PrimitiveType;
+
+                struct BuilderOwner(*mut AttributeMapBuilder);
+                impl Drop for BuilderOwner {
+                    fn drop(&mut self) {
+                        unsafe { ffi_attribute_map_builder_destroy(self.0) }
+                    }
+                }
+
+                fn set_string_array(amb: *mut AttributeMapBuilder, key: &str, values: &[String], rows: usize)
+                                    -> Result<(), crate::prt::PrtError>
+                {
+                    let key_cstring = to_cstring(key, key)?;
+                    let owners = values.iter()
+                        .map(|s| to_cstring(key, s))
+                        .collect::<Result<Vec<ffi::CString>, crate::prt::PrtError>>()?;
+                    let ptrs: Vec<*const ffi::c_char> = owners.iter().map(|s| s.as_ptr()).collect();
+                    unsafe {
+                        ffi_attribute_map_builder_set_string_array(amb, key_cstring.as_ptr(), ptrs.as_ptr(),
+                                                                   ptrs.len(), rows);
+                    }
+                    Ok(())
+                }
+
+                let amb = BuilderOwner(unsafe { ffi_attribute_map_builder_create() });
+                for (key, value) in entries {
+                    let key_cstring = to_cstring(key, key)?;
+                    let k = key_cstring.as_ptr();
+                    unsafe {
+                        match value {
+                            PrimitiveType::Undefined() => {} // nothing to set
+                            PrimitiveType::String(v) => {
+                                let v = to_cstring(key, v)?;
+                                ffi_attribute_map_builder_set_string(amb.0, k, v.as_ptr());
+                            }
+                            PrimitiveType::Float(v) => ffi_attribute_map_builder_set_float(amb.0, k, *v),
+                            PrimitiveType::Bool(v) => ffi_attribute_map_builder_set_bool(amb.0, k, *v),
+                            PrimitiveType::Int(v) => ffi_attribute_map_builder_set_int(amb.0, k, *v),
+                            PrimitiveType::StringArray(v) => set_string_array(amb.0, key, v, 1)?,
+                            PrimitiveType::FloatArray(v) =>
+                                ffi_attribute_map_builder_set_float_array(amb.0, k, v.as_ptr(), v.len(), 1),
+                            PrimitiveType::BoolArray(v) =>
+                                ffi_attribute_map_builder_set_bool_array(amb.0, k, v.as_ptr(), v.len(), 1),
+                            PrimitiveType::IntArray(v) =>
+                                ffi_attribute_map_builder_set_int_array(amb.0, k, v.as_ptr(), v.len(), 1),
+                            PrimitiveType::StringArray2D(v) => set_string_array(amb.0, key, v.values(), v.rows())?,
+                            PrimitiveType::FloatArray2D(v) =>
+                                ffi_attribute_map_builder_set_float_array(amb.0, k, v.values().as_ptr(),
+                                                                          v.values().len(), v.rows()),
+                            PrimitiveType::BoolArray2D(v) =>
+                                ffi_attribute_map_builder_set_bool_array(amb.0, k, v.values().as_ptr(),
+                                                                         v.values().len(), v.rows()),
+                            PrimitiveType::IntArray2D(v) =>
+                                ffi_attribute_map_builder_set_int_array(amb.0, k, v.values().as_ptr(),
+                                                                        v.values().len(), v.rows()),
+                        }
+                    }
+                }
+
+                let handle = unsafe { ffi_attribute_map_builder_create_attribute_map(amb.0) };
+                if handle.is_null() {
+                    return Err(crate::prt::PrtError {
+                        message: "Could not create attribute map".to_string(),
+                        status: None,
+                    });
+                }
+                Ok(AttributeMapOwner { handle })
+            }
+
+            pub(crate) fn as_ptr(&self) -> *const AttributeMap {
+                self.handle
+            }
+        }
+
+        impl Drop for AttributeMapOwner {
+            fn drop(&mut self) {
+                unsafe { ffi_attribute_map_destroy(self.handle) }
+            }
+        }
+
+        #[repr(C)]
+        pub(crate) struct ResolveMap {
             dummy: i32,
         }
 
+        #[link(name = "bindings", kind = "static")]
+        unsafe extern "C" {
+            pub(crate) fn ffi_create_resolve_map(uri: *const ffi::c_char,
+                                                 status: *mut crate::prt::Status) -> *const ResolveMap;
+            pub(crate) fn ffi_resolve_map_destroy(resolve_map: *const ResolveMap);
+        }
+
         #[repr(C)]
         pub(crate) struct InitialShapeWrapper {
             // see cpp/bindings.h
@@ -433,6 +1937,14 @@ pub mod prt {
             indices_count: libc::size_t,
             face_counts: *const u32,
             face_counts_count: libc::size_t,
+            holes: *const u32,
+            holes_count: libc::size_t,
+            uv_sets: *const UvSetWrapper,
+            uv_sets_count: libc::size_t,
+            textures: *const *const ffi::c_char,
+            textures_count: libc::size_t,
+            texture_face_ranges: *const u32,
+            texture_face_ranges_count: libc::size_t,
 
             rule_file: *const ffi::c_char,
             start_rule: *const ffi::c_char,
@@ -442,22 +1954,98 @@ pub mod prt {
             resolve_map: *const ResolveMap,
         }
 
+        #[repr(C)]
+        pub(crate) struct UvSetWrapper {
+            // see cpp/bindings.h
+            uvs: *const f64,
+            uvs_count: libc::size_t,
+            uv_indices: *const u32,
+            uv_indices_count: libc::size_t,
+            face_ranges: *const u32,
+            face_ranges_count: libc::size_t,
+        }
+
         pub(crate) struct InitialShapeAdaptor<'a> {
             initial_shape: &'a Box<crate::prt::InitialShape>,
 
+            ffi_holes_owner: Vec<u32>,
+            ffi_uv_sets_owner: Vec<UvSetWrapper>,
+            #[allow(dead_code)] // keeps the texture pointers valid
+            ffi_textures_owner: Vec<ffi::CString>,
+            ffi_texture_ptrs_owner: Vec<*const ffi::c_char>,
+            ffi_attributes_owner: AttributeMapOwner,
             ffi_rule_file_owner: ffi::CString,
             ffi_start_rule_owner: ffi::CString,
             ffi_name_owner: ffi::CString,
         }
 
+        const HOLE_DELIM: u32 = u32::MAX; // see prt::InitialShapeBuilder::HOLE_DELIM
+
+        // flattens the holes into the PRT format: face index, hole face indices, HOLE_DELIM, ...
+        pub(crate) fn flatten_holes(holes: &[crate::prt::FaceHoles]) -> Vec<u32> {
+            holes.iter()
+                .flat_map(|h| std::iter::once(h.face).chain(h.holes.iter().copied()).chain(std::iter::once(HOLE_DELIM)))
+                .collect()
+        }
+
+        pub(crate) fn unflatten_holes(holes: &[u32]) -> Vec<crate::prt::FaceHoles> {
+            holes.split(|&i| i == HOLE_DELIM)
+                .filter_map(|h| h.split_first())
+                .map(|(&face, holes)| crate::prt::FaceHoles { face, holes: holes.to_vec() })
+                .collect()
+        }
+
         impl InitialShapeAdaptor<'_> {
-            pub(crate) fn adapt(initial_shape: &Box<crate::prt::InitialShape>) -> InitialShapeAdaptor {
-                InitialShapeAdaptor {
+            // fails with the index and field of the initial shape if it cannot be passed to PRT
+            pub(crate) fn adapt(index: usize, initial_shape: &Box<crate::prt::InitialShape>)
+                                -> Result<InitialShapeAdaptor, crate::prt::PrtError>
+            {
+                let invalid_field = |field: &str, reason: &str| crate::prt::PrtError {
+                    message: format!("Initial shape {} ('{}'): {} {}", index, initial_shape.name, field, reason),
+                    status: Some(crate::prt::Status::STATUS_ILLEGAL_VALUE),
+                };
+                let to_cstring = |field: &str, value: &str| ffi::CString::new(value)
+                    .map_err(|_| invalid_field(field, "contains a NUL byte"));
+
+                // check the strings first, they do not need PRT
+                let ffi_rule_file_owner = initial_shape.rule_file.ffi_to_cstring()
+                    .map_err(|reason| invalid_field("rule_file", reason))?;
+                let ffi_start_rule_owner = to_cstring("start_rule", &initial_shape.start_rule)?;
+                let ffi_name_owner = to_cstring("name", &initial_shape.name)?;
+                let ffi_textures_owner: Vec<ffi::CString> = initial_shape.textures.iter().enumerate()
+                    .map(|(i, t)| t.ffi_to_cstring()
+                        .map_err(|reason| invalid_field(&format!("textures[{}]", i), reason)))
+                    .collect::<Result<_, _>>()?;
+                let ffi_texture_ptrs_owner = ffi_textures_owner.iter().map(|t| t.as_ptr()).collect();
+
+                let ffi_uv_sets_owner = initial_shape.uv_sets.iter()
+                    .map(|uv_set| UvSetWrapper {
+                        uvs: uv_set.uvs.as_ptr(),
+                        uvs_count: uv_set.uvs.len(),
+                        uv_indices: uv_set.uv_indices.as_ptr(),
+                        uv_indices_count: uv_set.uv_indices.len(),
+                        face_ranges: uv_set.face_ranges.as_ptr(),
+                        face_ranges_count: uv_set.face_ranges.len(),
+                    })
+                    .collect();
+                let ffi_attributes_owner = AttributeMapOwner::from_entries(initial_shape.attributes.iter()
+                    .map(|(k, v)| (k.as_str(), v)))
+                    .map_err(|err| crate::prt::PrtError {
+                        message: format!("Initial shape {} ('{}'): attributes: {}", index, initial_shape.name,
+                                         err.message),
+                        ..err
+                    })?;
+                Ok(InitialShapeAdaptor {
                     initial_shape,
-                    ffi_rule_file_owner: initial_shape.rule_file.ffi_to_cstring(),
-                    ffi_start_rule_owner: ffi::CString::new(initial_shape.start_rule.as_bytes()).unwrap(),
-                    ffi_name_owner: ffi::CString::new(initial_shape.name.as_bytes()).unwrap(),
-                }
+                    ffi_holes_owner: flatten_holes(&initial_shape.holes),
+                    ffi_uv_sets_owner,
+                    ffi_textures_owner,
+                    ffi_texture_ptrs_owner,
+                    ffi_attributes_owner,
+                    ffi_rule_file_owner,
+                    ffi_start_rule_owner,
+                    ffi_name_owner,
+                })
             }
 
             pub(crate) fn get_ffi_wrapper(&self) -> InitialShapeWrapper {
@@ -468,11 +2056,19 @@ pub mod prt {
                     indices_count: self.initial_shape.indices.len(),
                     face_counts: self.initial_shape.face_counts.as_ptr(),
                     face_counts_count: self.initial_shape.face_counts.len(),
+                    holes: self.ffi_holes_owner.as_ptr(),
+                    holes_count: self.ffi_holes_owner.len(),
+                    uv_sets: self.ffi_uv_sets_owner.as_ptr(),
+                    uv_sets_count: self.ffi_uv_sets_owner.len(),
+                    textures: self.ffi_texture_ptrs_owner.as_ptr(),
+                    textures_count: self.ffi_texture_ptrs_owner.len(),
+                    texture_face_ranges: self.initial_shape.texture_face_ranges.as_ptr(),
+                    texture_face_ranges_count: self.initial_shape.texture_face_ranges.len(),
                     rule_file: self.ffi_rule_file_owner.as_ptr(),
                     start_rule: self.ffi_start_rule_owner.as_ptr(),
                     random_seed: self.initial_shape.random_seed,
                     name: self.ffi_name_owner.as_ptr(),
-                    attributes: null(), // TODO
+                    attributes: self.ffi_attributes_owner.as_ptr(),
                     resolve_map: null(), // TODO
                 };
             }
@@ -483,14 +2079,165 @@ pub mod prt {
             dummy: i32,
         }
 
+        pub(crate) type SetGeometryFn<T> = extern "C" fn(*mut T,
+                                                        *const f64, libc::size_t,
+                                                        *const u32, libc::size_t,
+                                                        *const u32, libc::size_t,
+                                                        *const u32, libc::size_t,
+                                                        *const ffi::c_char);
+
+        #[link(name = "bindings", kind = "static")]
+        unsafe extern "C" {
+            pub(crate) fn ffi_cache_create() -> *mut Cache;
+            pub(crate) fn ffi_cache_destroy(cache: *mut Cache);
+            pub(crate) fn ffi_resolve_geometry(uri: *const ffi::c_char,
+                                               resolve_map: *const ResolveMap,
+                                               cache: *mut Cache,
+                                               geometry_binding: *mut ffi::c_void) -> crate::prt::Status;
+            pub(crate) fn ffi_read_binary_data(key_or_uri: *const ffi::c_char,
+                                               resolve_map: *const ResolveMap,
+                                               cache: *mut Cache,
+                                               data_binding: *mut ffi::c_void) -> crate::prt::Status;
+        }
+
+        #[repr(C)]
+        pub(crate) struct AbstractBinaryDataBinding<T> {
+            pub(crate) set_data: extern "C" fn(*mut T, *const u8, libc::size_t),
+            pub(crate) context: *mut T,
+        }
+
+        #[repr(C)]
+        pub(crate) struct AbstractGeometryBinding<T> {
+            pub(crate) set_geometry: SetGeometryFn<T>,
+            pub(crate) context: *mut T,
+        }
+
         #[repr(C)]
         pub(crate) struct OcclusionSet {
             dummy: i32,
         }
 
+        #[link(name = "bindings", kind = "static")]
+        unsafe extern "C" {
+            pub(crate) fn ffi_occlusion_set_create() -> *mut OcclusionSet;
+            pub(crate) fn ffi_occlusion_set_destroy(occl_set: *mut OcclusionSet);
+        }
+
         #[repr(C)]
         pub(crate) struct AbstractCallbacksBinding<T> where T: crate::prt::Callbacks {
             pub(crate) context: *mut T,
+            pub(crate) output_directory: *const ffi::c_char, // may be null
+            pub(crate) is_canceled: extern "C" fn(*mut T) -> bool,
+            pub(crate) on_progress: extern "C" fn(*mut T, libc::size_t, libc::size_t, ProgressState,
+                                                  crate::prt::Status, f64),
+            pub(crate) on_mesh: extern "C" fn(*mut T, libc::size_t, *const CapturedMeshWrapper),
+            pub(crate) on_report: extern "C" fn(*mut T, libc::size_t, *const ffi::c_char, *const PrimitiveWrapper),
+            pub(crate) on_attribute: extern "C" fn(*mut T, libc::size_t, *const ffi::c_char, *const PrimitiveWrapper),
+        }
+
+        #[repr(C)]
+        pub(crate) struct CapturedUvSetWrapper {
+            // see cpp/codecs/GeometryCaptureCallbacks.h
+            uvs: *const f64,
+            uvs_count: libc::size_t,
+            uv_indices: *const u32,
+            uv_indices_count: libc::size_t,
+            face_uv_counts: *const u32,
+            face_uv_counts_count: libc::size_t,
+        }
+
+        #[repr(C)]
+        pub(crate) struct CapturedMeshWrapper {
+            // see cpp/codecs/GeometryCaptureCallbacks.h
+            vertex_coords: *const f64,
+            vertex_coords_count: libc::size_t,
+            normals: *const f64,
+            normals_count: libc::size_t,
+            face_counts: *const u32,
+            face_counts_count: libc::size_t,
+            vertex_indices: *const u32,
+            vertex_indices_count: libc::size_t,
+            normal_indices: *const u32,
+            normal_indices_count: libc::size_t,
+            uv_sets: *const CapturedUvSetWrapper,
+            uv_sets_count: libc::size_t,
+            material: *const CapturedMaterialWrapper, // null if the mesh has no material
+        }
+
+        #[repr(C)]
+        pub(crate) struct CapturedTextureWrapper {
+            // see cpp/codecs/GeometryCaptureCallbacks.h
+            key: *const ffi::c_char,
+            uri: *const ffi::c_char,
+            uv_set: u32,
+        }
+
+        #[repr(C)]
+        pub(crate) struct CapturedMaterialWrapper {
+            // see cpp/codecs/GeometryCaptureCallbacks.h
+            color: [f64; 3],
+            opacity: f64,
+            metallic: f64,
+            roughness: f64,
+            textures: *const CapturedTextureWrapper,
+            textures_count: libc::size_t,
+        }
+
+        impl CapturedMaterialWrapper {
+            fn to_captured_material(&self) -> crate::prt::CapturedMaterial {
+                use crate::helpers::from_char_ptr_to_string;
+                let textures = if self.textures_count == 0 {
+                    &[]
+                } else {
+                    unsafe { std::slice::from_raw_parts(self.textures, self.textures_count) }
+                };
+                crate::prt::CapturedMaterial {
+                    color: self.color,
+                    opacity: self.opacity,
+                    metallic: self.metallic,
+                    roughness: self.roughness,
+                    textures: textures.iter().map(|t| crate::prt::CapturedTexture {
+                        key: from_char_ptr_to_string(t.key),
+                        uri: from_char_ptr_to_string(t.uri),
+                        uv_set: t.uv_set,
+                    }).collect(),
+                }
+            }
+        }
+
+        impl CapturedMeshWrapper {
+            pub(crate) fn to_captured_mesh(&self) -> crate::prt::CapturedMesh {
+                use crate::helpers::from_raw_parts_to_vec;
+                unsafe {
+                    let uv_sets = if self.uv_sets_count == 0 {
+                        &[]
+                    } else {
+                        std::slice::from_raw_parts(self.uv_sets, self.uv_sets_count)
+                    };
+                    crate::prt::CapturedMesh {
+                        vertex_coords: from_raw_parts_to_vec(self.vertex_coords, self.vertex_coords_count),
+                        normals: from_raw_parts_to_vec(self.normals, self.normals_count),
+                        face_counts: from_raw_parts_to_vec(self.face_counts, self.face_counts_count),
+                        vertex_indices: from_raw_parts_to_vec(self.vertex_indices, self.vertex_indices_count),
+                        normal_indices: from_raw_parts_to_vec(self.normal_indices, self.normal_indices_count),
+                        uv_sets: uv_sets.iter().map(|uv_set| crate::prt::CapturedUvSet {
+                            uvs: from_raw_parts_to_vec(uv_set.uvs, uv_set.uvs_count),
+                            uv_indices: from_raw_parts_to_vec(uv_set.uv_indices, uv_set.uv_indices_count),
+                            face_uv_counts: from_raw_parts_to_vec(uv_set.face_uv_counts,
+                                                                  uv_set.face_uv_counts_count),
+                        }).collect(),
+                        material: self.material.as_ref().map(|m| m.to_captured_material()),
+                    }
+                }
+            }
+        }
+
+        #[allow(dead_code)] // constructed on the native side
+        #[repr(C)]
+        pub(crate) enum ProgressState {
+            Started,
+            Finished,
+            Failed,
         }
 
         #[link(name = "bindings", kind = "static")]
@@ -500,11 +2247,22 @@ pub mod prt {
                                        occlusion_handles: *const u64, // see prt::OcclusionSet::Handle
                                        encoders: *const *const libc::wchar_t,
                                        encoders_count: libc::size_t,
-                                       encoder_options: *const AttributeMap,
+                                       encoder_options: *const *const AttributeMap,
                                        callbacks: *mut ffi::c_void,
                                        cache: *mut Cache,
                                        occl_set: *const OcclusionSet,
                                        generate_options: *const AttributeMap) -> crate::prt::Status;
+
+            pub(crate) fn ffi_generate_occluders(initial_shapes: *const *const InitialShapeWrapper,
+                                                 initial_shapes_count: libc::size_t,
+                                                 occlusion_handles: *mut u64, // see prt::OcclusionSet::Handle
+                                                 encoders: *const *const libc::wchar_t,
+                                                 encoders_count: libc::size_t,
+                                                 encoder_options: *const *const AttributeMap,
+                                                 callbacks: *mut ffi::c_void,
+                                                 cache: *mut Cache,
+                                                 occl_set: *mut OcclusionSet,
+                                                 generate_options: *const AttributeMap) -> crate::prt::Status;
         }
 
         #[repr(C)]
@@ -515,8 +2273,8 @@ pub mod prt {
 
         #[link(name = "bindings", kind = "static")]
         unsafe extern "C" {
-            pub(crate) fn ffi_add_log_handler(log_handler: *mut ffi::c_void);
-            pub(crate) fn ffi_remove_log_handler(log_handler: *mut ffi::c_void);
+            pub(crate) fn ffi_add_log_handler(log_handler: *mut ffi::c_void) -> bool;
+            pub(crate) fn ffi_remove_log_handler(context: *mut ffi::c_void) -> *mut ffi::c_void;
         }
 
         unsafe extern "C" {
@@ -525,6 +2283,37 @@ pub mod prt {
             pub(crate) fn prt_log(msg: *const libc::wchar_t, level: crate::prt::LogLevel);
         }
 
+        #[allow(dead_code)] // discriminants are read on the native side
+        #[repr(C)]
+        pub(crate) enum CodecKind {
+            Encoder,
+            Decoder,
+        }
+
+        #[repr(C)]
+        pub(crate) struct CodecInfoWrapper {
+            // see cpp/bindings.h
+            pub(crate) id: *const ffi::c_char,
+            pub(crate) name: *const ffi::c_char,
+            pub(crate) description: *const ffi::c_char,
+            pub(crate) extensions: *const ffi::c_char,
+            pub(crate) content_types: *const i32,
+            pub(crate) content_type_count: usize,
+        }
+
+        #[repr(C)]
+        pub(crate) struct AbstractCodecInfoBinding<T> {
+            pub(crate) add_codec_info: extern "C" fn(*mut T, *const CodecInfoWrapper),
+            pub(crate) context: *mut T,
+        }
+
+        #[link(name = "bindings", kind = "static")]
+        unsafe extern "C" {
+            // lists all codecs of the given kind if codec_id is null
+            pub(crate) fn ffi_get_codec_infos(kind: CodecKind, codec_id: *const ffi::c_char,
+                                              codec_info_binding: *mut ffi::c_void) -> crate::prt::Status;
+        }
+
         #[repr(C)]
         pub(crate) struct Version {
             pub(crate) version_major: i32,
@@ -571,12 +2360,55 @@ pub mod prt {
             #[link_name = "\u{1}_ZN3prt20getStatusDescriptionENS_6StatusE"]
             pub(crate) fn ffi_get_status_description(input: crate::prt::Status) -> *const ffi::c_char;
         }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+            use crate::prt::{Array2D, PrimitiveType};
+
+            fn float_array_wrapper(values: &[f64], rows: libc::size_t) -> PrimitiveWrapper {
+                PrimitiveWrapper {
+                    primitive_type: 6,
+                    bool_value: false,
+                    float_value: 0.0,
+                    int_value: 0,
+                    string_value: null(),
+                    bool_array: null(),
+                    float_array: values.as_ptr(),
+                    int_array: null(),
+                    string_array: null(),
+                    count: values.len(),
+                    rows,
+                }
+            }
+
+            #[test]
+            fn primitive_wrapper_rows() {
+                let values = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+                assert_eq!(float_array_wrapper(&values, 1).to_primitive(), PrimitiveType::FloatArray(values.to_vec()));
+                assert_eq!(float_array_wrapper(&values, 0).to_primitive(), PrimitiveType::FloatArray(values.to_vec()));
+                assert_eq!(float_array_wrapper(&values, 2).to_primitive(),
+                           PrimitiveType::FloatArray2D(Array2D::new(values.to_vec(), 2).unwrap()));
+                assert_eq!(float_array_wrapper(&values, 4).to_primitive(), PrimitiveType::Undefined());
+            }
+        }
     }
 
     #[cfg(test)]
     mod tests {
         use super::*;
 
+        /// The initial shape fixture of the unit tests: a lot with the rule and name set, the geometry and the
+        /// seed are up to the test.
+        pub(crate) fn lot_builder(name: &str) -> InitialShapeBuilder {
+            let mut builder = InitialShapeBuilder::default();
+            builder
+                .rule_file(KeyOrUri::Key("bin/rule.cgb".to_string()))
+                .start_rule("Default$Lot".to_string())
+                .name(name.to_string());
+            builder
+        }
+
         fn assert_cstring(prefix: &str, raw_val: *const ffi::c_char, expected_val: &str) {
             let val = crate::helpers::from_char_ptr_to_string(raw_val);
             assert_string(prefix, val, expected_val);
@@ -627,6 +2459,260 @@ pub mod prt {
             }
         }
 
+        #[test]
+        fn cancellation_token_is_shared_by_clones() {
+            let token = CancellationToken::new();
+            let callbacks = FileCallbacks::default().with_cancellation_token(token.clone());
+            assert!(!callbacks.is_canceled());
+
+            let remote_token = token.clone();
+            std::thread::spawn(move || remote_token.cancel()).join().unwrap();
+            assert!(token.is_canceled());
+            assert!(callbacks.is_canceled());
+        }
+
+        #[test]
+        fn progress_listener_channel() {
+            let (sender, receiver) = mpsc::channel();
+            let mut callbacks = FileCallbacks::default().with_progress_listener(sender);
+            let event = ProgressEvent {
+                initial_shape_index: 1,
+                initial_shape_count: 2,
+                progress: ShapeProgress::Finished,
+                elapsed: time::Duration::from_millis(10),
+            };
+            callbacks.on_progress(event.clone());
+            drop(callbacks);
+            assert_eq!(receiver.iter().collect::<Vec<ProgressEvent>>(), vec![event]);
+        }
+
+        #[test]
+        fn shape_value_collector() {
+            let mut callbacks = FileCallbacks::default();
+            let mut collector = ShapeValueCollector {
+                callbacks: &mut callbacks,
+                reports: vec![Reports::new(), Reports::new()],
+                attributes: vec![AttributeValues::new(), AttributeValues::new()],
+            };
+            collector.on_report(1, "Floor Area", PrimitiveType::Float(120.5));
+            collector.on_attribute(0, "Default$height", PrimitiveType::Float(10.0));
+            collector.on_report(2, "out of range", PrimitiveType::Bool(true)); // ignored
+
+            assert!(collector.reports[0].is_empty());
+            assert_eq!(collector.reports[1]["Floor Area"], PrimitiveType::Float(120.5));
+            assert_eq!(collector.attributes[0]["Default$height"], PrimitiveType::Float(10.0));
+            assert!(collector.attributes[1].is_empty());
+        }
+
+        fn courtyard_builder() -> InitialShapeBuilder {
+            let mut builder = InitialShapeBuilder::default();
+            builder
+                .vertex_coords(vec![0.0, 0.0, 0.0, 0.0, 0.0, 3.0, 3.0, 0.0, 3.0, 3.0, 0.0, 0.0,
+                                    1.0, 0.0, 1.0, 2.0, 0.0, 1.0, 2.0, 0.0, 2.0, 1.0, 0.0, 2.0])
+                .indices(vec![0, 1, 2, 3, 4, 5, 6, 7])
+                .face_counts(vec![4, 4])
+                .rule_file(KeyOrUri::Key("bin/rule.cgb".to_string()))
+                .start_rule("Default$Init".to_string())
+                .random_seed(0)
+                .name("courtyard".to_string());
+            builder
+        }
+
+        #[test]
+        fn initial_shape_with_holes() {
+            let initial_shape = courtyard_builder()
+                .holes(vec![FaceHoles { face: 0, holes: vec![1] }])
+                .build().unwrap();
+            let flat_holes = prt_ffi::flatten_holes(&initial_shape.holes);
+            assert_eq!(flat_holes, vec![0, 1, u32::MAX]);
+            assert_eq!(prt_ffi::unflatten_holes(&flat_holes), initial_shape.holes);
+        }
+
+        #[test]
+        fn initial_shape_with_invalid_holes() {
+            let missing_face = courtyard_builder()
+                .holes(vec![FaceHoles { face: 0, holes: vec![2] }])
+                .build();
+            assert!(matches!(missing_face, Err(InitialShapeBuilderError::ValidationError(_))));
+
+            let self_hole = courtyard_builder()
+                .holes(vec![FaceHoles { face: 1, holes: vec![1] }])
+                .build();
+            assert!(self_hole.is_err());
+
+            let nested_hole = courtyard_builder()
+                .holes(vec![FaceHoles { face: 0, holes: vec![1] }, FaceHoles { face: 1, holes: vec![0] }])
+                .build();
+            assert!(nested_hole.is_err());
+        }
+
+        #[test]
+        fn initial_shape_with_invalid_uvs() {
+            let odd_uvs = courtyard_builder()
+                .uv_sets(vec![UvSet { uvs: vec![0.0, 0.0, 1.0], uv_indices: vec![0], face_ranges: vec![] }])
+                .build();
+            assert!(odd_uvs.is_err());
+
+            let out_of_range = courtyard_builder()
+                .uv_sets(vec![UvSet { uvs: vec![0.0, 0.0, 1.0, 1.0], uv_indices: vec![0, 1, 2], face_ranges: vec![] }])
+                .build();
+            assert!(out_of_range.is_err());
+        }
+
+        #[test]
+        fn array_2d_rows_and_columns() {
+            let matrix = Array2D::new(vec![1, 2, 3, 4, 5, 6], 2).unwrap();
+            assert_eq!(matrix.rows(), 2);
+            assert_eq!(matrix.columns(), 3);
+            assert_eq!(matrix.row(1), Some(&[4, 5, 6][..]));
+            assert_eq!(matrix.row(2), None);
+            assert_eq!(matrix.get(0, 2), Some(&3));
+
+            assert!(Array2D::new(vec![1, 2, 3], 2).is_none());
+            assert!(Array2D::<i32>::new(vec![], 0).is_none());
+            assert!(Array2D::<i32>::new(vec![1, 2], 0).is_none());
+
+            // no columns
+            let empty_rows = Array2D::<i32>::new(vec![], 3).unwrap();
+            assert_eq!(empty_rows.columns(), 0);
+            assert_eq!(empty_rows.row(2), Some(&[][..]));
+            assert_eq!(empty_rows.row(usize::MAX), None);
+            assert_eq!(matrix.row(usize::MAX), None);
+        }
+
+        #[test]
+        fn initial_shape_with_invalid_strings() {
+            let builder = || {
+                let mut builder = InitialShapeBuilder::default();
+                builder.vertex_coords(vec![0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 1.0])
+                    .indices(vec![0, 1, 2])
+                    .face_counts(vec![3])
+                    .rule_file(KeyOrUri::Key("bin/rule.cgb".to_string()))
+                    .start_rule("Default$Lot".to_string())
+                    .random_seed(0)
+                    .name("lot".to_string());
+                builder
+            };
+
+            let undefined_rule_file = Box::new(builder().rule_file(KeyOrUri::Undefined).build().unwrap());
+            let err = prt_ffi::InitialShapeAdaptor::adapt(3, &undefined_rule_file).err().unwrap();
+            assert_eq!(err.message, "Initial shape 3 ('lot'): rule_file is undefined");
+            assert_eq!(err.status, Some(Status::STATUS_ILLEGAL_VALUE));
+
+            let nul_start_rule = Box::new(builder().start_rule("Default\0Lot".to_string()).build().unwrap());
+            let err = prt_ffi::InitialShapeAdaptor::adapt(0, &nul_start_rule).err().unwrap();
+            assert_eq!(err.message, "Initial shape 0 ('lot'): start_rule contains a NUL byte");
+
+            let undefined_texture = Box::new(builder()
+                .textures(vec![KeyOrUri::Key("a.jpg".to_string()), KeyOrUri::Undefined])
+                .build().unwrap());
+            let err = prt_ffi::InitialShapeAdaptor::adapt(1, &undefined_texture).err().unwrap();
+            assert_eq!(err.message, "Initial shape 1 ('lot'): textures[1] is undefined");
+        }
+
+        #[test]
+        fn thread_safety_markers() {
+            fn send_and_sync<T: Send + Sync>() {}
+            fn send<T: Send>() {}
+            send_and_sync::<PrtContext>();
+            send_and_sync::<ResolveMap>();
+            send_and_sync::<Cache>();
+            send_and_sync::<InitialShape>();
+            send_and_sync::<GenerateJob>();
+            send_and_sync::<BatchGenerator>();
+            send::<OcclusionSet>();
+            send::<FileCallbacks>();
+        }
+
+        #[cfg(feature = "serde")]
+        #[test]
+        fn generate_job_json_round_trip() {
+            let shape = lot_builder("lot")
+                .vertex_coords(vec![0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 1.0])
+                .indices(vec![0, 1, 2])
+                .face_counts(vec![3])
+                .random_seed(42)
+                .attributes([("Default$height".to_string(), PrimitiveType::Float(10.0))].into())
+                .build()
+                .unwrap();
+            let job = GenerateJob {
+                initial_shapes: vec![Box::new(shape)],
+                encoders: vec![encoders::OBJ_ENCODER_ID.to_string()],
+                encoder_options: vec![[("baseName".to_string(), PrimitiveType::from("lot"))].into()],
+                generate_options: Some(GenerateOptionsBuilder::default().number_worker_threads(2).build().unwrap()),
+            };
+
+            let json = serde_json::to_string(&job).unwrap();
+            let restored: GenerateJob = serde_json::from_str(&json).unwrap();
+            assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+            assert_eq!(restored.initial_shapes[0].attributes["Default$height"], PrimitiveType::Float(10.0));
+            assert_eq!(restored.generate_options, job.generate_options);
+
+            // the builder validation also applies when deserializing
+            let invalid = json.replace("\"holes\":[]", "\"holes\":[{\"face\":5,\"holes\":[0]}]");
+            assert_ne!(invalid, json);
+            assert!(serde_json::from_str::<GenerateJob>(&invalid).is_err());
+        }
+
+        #[test]
+        fn encoder_option_annotations() {
+            let annotation = |name: &str, arguments: &[(&str, PrimitiveType)]| OptionAnnotation {
+                name: name.to_string(),
+                arguments: arguments.iter().map(|(k, v)| (k.to_string(), v.clone())).collect(),
+            };
+            let info = EncoderOptionInfo::new("scale".to_string(), PrimitiveType::Float(1.0), OptionState::Enabled,
+                                              vec![annotation("@Range", &[("max", PrimitiveType::Int(10)),
+                                                                          ("min", PrimitiveType::Float(0.5))]),
+                                                   annotation("@Description", &[("", PrimitiveType::from("Scale"))])]);
+            assert_eq!(info.range, Some((0.5, 10.0)));
+            assert_eq!(info.description.as_deref(), Some("Scale"));
+            assert!(info.enum_values.is_empty());
+
+            let info = EncoderOptionInfo::new("mode".to_string(), PrimitiveType::from("a"), OptionState::Enabled,
+                                              vec![annotation("@Enum", &[("", PrimitiveType::from("a")),
+                                                                         ("", PrimitiveType::from("b"))])]);
+            assert_eq!(info.enum_values, vec![PrimitiveType::from("a"), PrimitiveType::from("b")]);
+            assert_eq!(info.range, None);
+            assert_eq!(info.annotations.len(), 1);
+        }
+
+        #[test]
+        fn typed_encoder_options() {
+            use encoders::EncoderSettings;
+
+            let mut extra_options = EncoderOptions::new();
+            extra_options.insert("triangulate".to_string(), PrimitiveType::Bool(false));
+            let (encoder_id, options) = encoders::ObjEncoderOptionsBuilder::default()
+                .base_name("building")
+                .global_offset([1.0, 2.0, 3.0])
+                .triangulate(true)
+                .extra_options(extra_options)
+                .build().unwrap()
+                .into_encoder_setup();
+
+            assert_eq!(encoder_id, "com.esri.prt.codecs.OBJEncoder");
+            assert_eq!(options.len(), 3);
+            assert_eq!(options.get("baseName"), Some(&PrimitiveType::String("building".to_string())));
+            assert_eq!(options.get("globalOffset"), Some(&PrimitiveType::FloatArray(vec![1.0, 2.0, 3.0])));
+            assert_eq!(options.get("triangulate"), Some(&PrimitiveType::Bool(false)));
+        }
+
+        #[test]
+        fn generate_extra_options_win() {
+            let mut extra_options = collections::HashMap::new();
+            extra_options.insert("numberWorkerThreads".to_string(), PrimitiveType::Int(2));
+            let options = GenerateOptionsBuilder::default()
+                .number_worker_threads(4)
+                .cache_shape_tree(true)
+                .extra_options(extra_options)
+                .build().unwrap()
+                .to_options();
+
+            assert_eq!(options.len(), 2);
+            assert_eq!(options.get("numberWorkerThreads"), Some(&PrimitiveType::Int(2)));
+            assert_eq!(options.get("cacheShapeTree"), Some(&PrimitiveType::Bool(true)));
+        }
+
         #[test]
         fn create_attribute_map() {
             let mut map = collections::HashMap::new();
@@ -637,13 +2723,27 @@ pub mod prt {
 }
 
 mod helpers {
-    use std::{ffi, fs, path};
+    use std::{ffi, fs, path, sync::mpsc};
+
+    /// Used by the channel implementations of the callback traits: a dropped receiver must not fail the
+    /// generate call, the value is discarded instead.
+    pub(crate) fn send_or_discard<T>(sender: &mpsc::Sender<T>, value: T) {
+        let _ = sender.send(value);
+    }
 
     pub(crate) fn from_char_ptr_to_string(cchar_ptr: *const ffi::c_char) -> String {
         let val_cstr = unsafe { ffi::CStr::from_ptr(cchar_ptr) };
         return val_cstr.to_str().unwrap_or_default().to_string();
     }
 
+    /// Copies a native array, ptr may be null if count is zero.
+    pub(crate) unsafe fn from_raw_parts_to_vec<T: Clone>(ptr: *const T, count: libc::size_t) -> Vec<T> {
+        if count == 0 {
+            return Vec::new();
+        }
+        unsafe { std::slice::from_raw_parts(ptr, count) }.to_vec()
+    }
+
     #[allow(dead_code)]
     #[cfg(target_os = "linux")]
     fn wchar_is_utf32() -> bool {
@@ -685,6 +2785,11 @@ mod helpers {
     }
 
     // TODO: deduplicate with get_dependencies_path in build.rs
+    /// Location of the custom PRT extensions installed by build.rs.
+    pub fn get_codecs_path() -> path::PathBuf {
+        path::PathBuf::from(env!("OUT_DIR")).join("codecs")
+    }
+
     pub fn get_cesdk_path() -> path::PathBuf {
         let out_dir = env!("OUT_DIR");
         let crate_root = path::PathBuf::from(out_dir);
@@ -0,0 +1,229 @@
+//! Generates large numbers of initial shapes in chunks on several threads. The results are streamed chunk by
+//! chunk, so neither all initial shapes nor all results have to be held in memory at once.
+
+use std::sync::{mpsc, Arc, Mutex, PoisonError};
+use std::thread;
+
+use derive_builder::Builder;
+
+use crate::prt::{try_generate, Cache, Callbacks, EncoderOptions, GenerateInputs, GenerateOptions, InitialShape,
+                 PrtError};
+
+const DEFAULT_CHUNK_SIZE: usize = 1000;
+
+fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// The outcome of a single chunk, passed to the `on_chunk` callback of [`BatchGenerator::generate`].
+pub struct ChunkResult<C> {
+    /// The position of the chunk in the batch. Chunks finish in any order.
+    pub chunk_index: usize,
+    /// The index of the first initial shape of the chunk in the batch. The callbacks receive the initial shape
+    /// indices within the chunk, add this to get the index in the batch.
+    pub first_shape_index: usize,
+    pub shape_count: usize,
+    pub result: Result<(), PrtError>,
+    /// The callbacks the chunk has been generated with, e.g. holding the collected reports or meshes.
+    pub callbacks: C,
+}
+
+// the unit of work handed to the worker threads
+struct Chunk {
+    index: usize,
+    first_shape_index: usize,
+    initial_shapes: Box<[Box<InitialShape>]>,
+}
+
+// the workers share the receiving end, it is gone once the last worker returned or panicked
+type ChunkReceiver = Arc<Mutex<mpsc::Receiver<Chunk>>>;
+
+/// Splits the initial shapes into chunks and generates the chunks concurrently against a shared [`Cache`],
+/// every chunk is a separate generate call with its own callbacks. Note that PRT parallelizes each generate
+/// call as well, see the `number_worker_threads` generate option.
+#[derive(Builder)]
+#[builder(pattern = "owned", build_fn(validate = "Self::validate"))]
+pub struct BatchGenerator {
+    encoders: Vec<String>,
+    /// One entry per encoder, defaults to empty options for every encoder.
+    #[builder(default)]
+    encoder_options: Vec<EncoderOptions>,
+    #[builder(default, setter(strip_option))]
+    generate_options: Option<GenerateOptions>,
+    /// The number of initial shapes per generate call.
+    #[builder(default = "DEFAULT_CHUNK_SIZE")]
+    chunk_size: usize,
+    /// The number of chunks generated at the same time, defaults to the available parallelism.
+    /// Ignored if a thread pool is set.
+    #[builder(default = "default_threads()")]
+    threads: usize,
+    /// Shared by all chunks, a new cache is created for every batch if not set.
+    #[builder(default, setter(strip_option))]
+    cache: Option<Arc<Cache>>,
+    /// Runs the chunks on this pool instead of dedicated threads, one chunk per pool thread at a time.
+    /// [`BatchGenerator::generate`] must not be called from a thread of this pool, it would wait for its own
+    /// thread to become free.
+    #[cfg(feature = "rayon")]
+    #[builder(default, setter(strip_option))]
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
+}
+
+impl BatchGeneratorBuilder {
+    fn validate(&self) -> Result<(), String> {
+        if self.chunk_size == Some(0) {
+            return Err("The chunk size must be at least 1".to_string());
+        }
+        if self.threads == Some(0) {
+            return Err("The number of threads must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl BatchGenerator {
+    /// Pulls the initial shapes chunk by chunk and generates them with new callbacks from `make_callbacks`
+    /// (called with the chunk index on the worker thread). `on_chunk` is called on the calling thread as soon
+    /// as a chunk is done. Only about two chunks per thread are in flight, the rest of the initial shapes stays
+    /// in the iterator until a worker becomes free.
+    ///
+    /// Fails only if the cache cannot be created or if called from a thread of the thread pool, the errors of
+    /// the individual chunks are in their results. A panic in `make_callbacks` or the callbacks is resumed on
+    /// the calling thread once the other chunks are done.
+    pub fn generate<I, C, F, R>(&self, initial_shapes: I, make_callbacks: F, mut on_chunk: R) -> Result<(), PrtError>
+        where I: IntoIterator<Item = Box<InitialShape>>,
+              C: Callbacks + Send,
+              F: Fn(usize) -> C + Sync,
+              R: FnMut(ChunkResult<C>)
+    {
+        #[cfg(feature = "rayon")]
+        if self.thread_pool.as_ref().is_some_and(|pool| pool.current_thread_index().is_some()) {
+            return Err(PrtError {
+                message: "The batch cannot be generated from a thread of its own thread pool".to_string(),
+                status: None,
+            });
+        }
+
+        let cache = match &self.cache {
+            Some(cache) => Arc::clone(cache),
+            None => Arc::new(Cache::new()?),
+        };
+        let encoder_options = if self.encoder_options.is_empty() {
+            self.encoders.iter().map(|_| EncoderOptions::new()).collect()
+        } else {
+            self.encoder_options.clone()
+        };
+
+        // bounded, i.e. the iterator is only advanced when a worker takes the next chunk
+        let (chunk_sender, chunk_receiver) = mpsc::sync_channel::<Chunk>(self.worker_count());
+        let (result_sender, result_receiver) = mpsc::channel::<ChunkResult<C>>();
+
+        let worker = |chunk_receiver: ChunkReceiver, result_sender: mpsc::Sender<ChunkResult<C>>| {
+            // the lock is released before generating, the workers only wait on it for the next chunk
+            loop {
+                let next = chunk_receiver.lock().unwrap_or_else(PoisonError::into_inner).recv();
+                let Ok(chunk) = next else { break };
+                let mut callbacks = make_callbacks(chunk.index);
+                let result = self.generate_chunk(&chunk.initial_shapes, &encoder_options, &cache, &mut callbacks);
+                let _ = result_sender.send(ChunkResult {
+                    chunk_index: chunk.index,
+                    first_shape_index: chunk.first_shape_index,
+                    shape_count: chunk.initial_shapes.len(),
+                    result,
+                    callbacks,
+                });
+            }
+        };
+
+        let feed = || {
+            let mut initial_shapes = initial_shapes.into_iter();
+            let mut first_shape_index = 0;
+            for index in 0.. {
+                let chunk: Box<[Box<InitialShape>]> = initial_shapes.by_ref().take(self.chunk_size).collect();
+                if chunk.is_empty() {
+                    break;
+                }
+                let shape_count = chunk.len();
+                if chunk_sender.send(Chunk { index, first_shape_index, initial_shapes: chunk }).is_err() {
+                    break; // all workers are gone, i.e. they panicked
+                }
+                first_shape_index += shape_count;
+                result_receiver.try_iter().for_each(&mut on_chunk);
+            }
+            drop(chunk_sender);
+            result_receiver.iter().for_each(&mut on_chunk);
+        };
+
+        self.run_workers(Arc::new(Mutex::new(chunk_receiver)), result_sender, worker, feed);
+        Ok(())
+    }
+
+    fn generate_chunk<C>(&self, initial_shapes: &[Box<InitialShape>], encoder_options: &[EncoderOptions],
+                         cache: &Cache, callbacks: &mut C) -> Result<(), PrtError>
+        where C: Callbacks + Send
+    {
+        let inputs = GenerateInputs {
+            initial_shapes,
+            encoders: &self.encoders,
+            encoder_options,
+            generate_options: self.generate_options.as_ref(),
+            cache: Some(cache),
+            ..GenerateInputs::default()
+        };
+        try_generate(&inputs, callbacks)
+    }
+
+    fn worker_count(&self) -> usize {
+        #[cfg(feature = "rayon")]
+        if let Some(pool) = &self.thread_pool {
+            return pool.current_num_threads();
+        }
+        self.threads
+    }
+
+    // spawns the workers (each with its own chunk receiver and result sender) and runs `feed` on the current thread
+    fn run_workers<C, W, D>(&self, chunk_receiver: ChunkReceiver, result_sender: mpsc::Sender<ChunkResult<C>>,
+                            worker: W, feed: D)
+        where C: Send,
+              W: Fn(ChunkReceiver, mpsc::Sender<ChunkResult<C>>) + Sync,
+              D: FnOnce()
+    {
+        let worker = &worker;
+
+        #[cfg(feature = "rayon")]
+        if let Some(pool) = &self.thread_pool {
+            return pool.in_place_scope(|scope| {
+                for _ in 0..self.worker_count() {
+                    let (chunk_receiver, result_sender) = (Arc::clone(&chunk_receiver), result_sender.clone());
+                    scope.spawn(move |_| worker(chunk_receiver, result_sender));
+                }
+                // the chunks and results end once all workers are done
+                drop((chunk_receiver, result_sender));
+                feed();
+            });
+        }
+
+        thread::scope(|scope| {
+            for _ in 0..self.worker_count() {
+                let (chunk_receiver, result_sender) = (Arc::clone(&chunk_receiver), result_sender.clone());
+                scope.spawn(move || worker(chunk_receiver, result_sender));
+            }
+            // the chunks and results end once all workers are done
+            drop((chunk_receiver, result_sender));
+            feed();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batch_generator_validation() {
+        let err = BatchGeneratorBuilder::default().encoders(vec![]).chunk_size(0).build().err().unwrap();
+        assert_eq!(err.to_string(), "The chunk size must be at least 1");
+        let err = BatchGeneratorBuilder::default().encoders(vec![]).threads(0).build().err().unwrap();
+        assert_eq!(err.to_string(), "The number of threads must be at least 1");
+        assert!(BatchGeneratorBuilder::default().encoders(vec![]).build().is_ok());
+    }
+}
@@ -0,0 +1,77 @@
+//! Generated geometry as Rust data instead of files, produced by the geometry capture encoder
+//! (see `cpp/codecs`), use [`crate::prt::encoders::GEOMETRY_CAPTURE_ENCODER_ID`] to enable it.
+
+use std::sync::mpsc;
+
+/// A uv set of a captured mesh, faces without uvs in this set have a uv count of zero.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CapturedUvSet {
+    /// u, v, u, v, ...
+    pub uvs: Vec<f64>,
+    pub uv_indices: Vec<u32>,
+    pub face_uv_counts: Vec<u32>,
+}
+
+/// A texture map of a material, the bytes can be read with [`crate::prt::ResolveMap::read_bytes`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CapturedTexture {
+    /// The CGA material key, e.g. "colormap" or "normalmap".
+    pub key: String,
+    pub uri: String,
+    /// The uv set of the mesh used by this texture map.
+    pub uv_set: u32,
+}
+
+/// The CGA material attributes of a mesh.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CapturedMaterial {
+    /// r, g, b in [0, 1]
+    pub color: [f64; 3],
+    pub opacity: f64,
+    pub metallic: f64,
+    pub roughness: f64,
+    /// Only the texture maps which are set.
+    pub textures: Vec<CapturedTexture>,
+}
+
+impl CapturedMaterial {
+    pub fn texture(&self, key: &str) -> Option<&CapturedTexture> {
+        self.textures.iter().find(|t| t.key == key)
+    }
+}
+
+/// A generated mesh in world coordinates. Vertices, normals and uvs are indexed separately per face vertex.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CapturedMesh {
+    /// x, y, z, x, y, z, ...
+    pub vertex_coords: Vec<f64>,
+    pub normals: Vec<f64>,
+    pub face_counts: Vec<u32>,
+    pub vertex_indices: Vec<u32>,
+    pub normal_indices: Vec<u32>,
+    /// The index in this vector is the uv set.
+    pub uv_sets: Vec<CapturedUvSet>,
+    pub material: Option<CapturedMaterial>,
+}
+
+impl CapturedMesh {
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_coords.len() / 3
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.face_counts.len()
+    }
+}
+
+/// Receives the meshes generated for the initial shapes, a shape can produce any number of meshes.
+pub trait GeometryConsumer {
+    fn on_mesh(&mut self, initial_shape_index: usize, mesh: CapturedMesh);
+}
+
+/// Feeds the meshes into a channel, e.g. to hand them to a simulation running on another thread.
+impl GeometryConsumer for mpsc::Sender<(usize, CapturedMesh)> {
+    fn on_mesh(&mut self, initial_shape_index: usize, mesh: CapturedMesh) {
+        crate::helpers::send_or_discard(self, (initial_shape_index, mesh));
+    }
+}
@@ -0,0 +1,369 @@
+//! Conversions between [`PrimitiveType`] and plain Rust values (and `serde_json::Value` with the `serde` feature).
+
+use crate::prt::{Array2D, PrimitiveType, PrtError, Status};
+
+impl From<String> for PrimitiveType {
+    fn from(value: String) -> Self {
+        PrimitiveType::String(value)
+    }
+}
+
+impl From<&str> for PrimitiveType {
+    fn from(value: &str) -> Self {
+        PrimitiveType::String(value.to_string())
+    }
+}
+
+impl From<f64> for PrimitiveType {
+    fn from(value: f64) -> Self {
+        PrimitiveType::Float(value)
+    }
+}
+
+impl From<bool> for PrimitiveType {
+    fn from(value: bool) -> Self {
+        PrimitiveType::Bool(value)
+    }
+}
+
+impl From<i32> for PrimitiveType {
+    fn from(value: i32) -> Self {
+        PrimitiveType::Int(value)
+    }
+}
+
+impl From<Vec<String>> for PrimitiveType {
+    fn from(value: Vec<String>) -> Self {
+        PrimitiveType::StringArray(value)
+    }
+}
+
+impl From<Vec<&str>> for PrimitiveType {
+    fn from(value: Vec<&str>) -> Self {
+        PrimitiveType::StringArray(value.into_iter().map(str::to_string).collect())
+    }
+}
+
+impl From<Vec<f64>> for PrimitiveType {
+    fn from(value: Vec<f64>) -> Self {
+        PrimitiveType::FloatArray(value)
+    }
+}
+
+impl From<Vec<bool>> for PrimitiveType {
+    fn from(value: Vec<bool>) -> Self {
+        PrimitiveType::BoolArray(value)
+    }
+}
+
+impl From<Vec<i32>> for PrimitiveType {
+    fn from(value: Vec<i32>) -> Self {
+        PrimitiveType::IntArray(value)
+    }
+}
+
+impl From<Array2D<String>> for PrimitiveType {
+    fn from(value: Array2D<String>) -> Self {
+        PrimitiveType::StringArray2D(value)
+    }
+}
+
+impl From<Array2D<f64>> for PrimitiveType {
+    fn from(value: Array2D<f64>) -> Self {
+        PrimitiveType::FloatArray2D(value)
+    }
+}
+
+impl From<Array2D<bool>> for PrimitiveType {
+    fn from(value: Array2D<bool>) -> Self {
+        PrimitiveType::BoolArray2D(value)
+    }
+}
+
+impl From<Array2D<i32>> for PrimitiveType {
+    fn from(value: Array2D<i32>) -> Self {
+        PrimitiveType::IntArray2D(value)
+    }
+}
+
+impl<T: Into<PrimitiveType>> From<Option<T>> for PrimitiveType {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(PrimitiveType::Undefined(), Into::into)
+    }
+}
+
+fn type_mismatch(expected: &str, value: &PrimitiveType) -> PrtError {
+    PrtError {
+        message: format!("Expected {}, got {:?}", expected, value),
+        status: Some(Status::STATUS_ILLEGAL_VALUE),
+    }
+}
+
+impl TryFrom<PrimitiveType> for String {
+    type Error = PrtError;
+
+    fn try_from(value: PrimitiveType) -> Result<Self, Self::Error> {
+        match value {
+            PrimitiveType::String(v) => Ok(v),
+            other => Err(type_mismatch("a string", &other)),
+        }
+    }
+}
+
+impl TryFrom<PrimitiveType> for f64 {
+    type Error = PrtError;
+
+    fn try_from(value: PrimitiveType) -> Result<Self, Self::Error> {
+        match value {
+            PrimitiveType::Float(v) => Ok(v),
+            PrimitiveType::Int(v) => Ok(v as f64), // lossless
+            other => Err(type_mismatch("a float", &other)),
+        }
+    }
+}
+
+impl TryFrom<PrimitiveType> for bool {
+    type Error = PrtError;
+
+    fn try_from(value: PrimitiveType) -> Result<Self, Self::Error> {
+        match value {
+            PrimitiveType::Bool(v) => Ok(v),
+            other => Err(type_mismatch("a bool", &other)),
+        }
+    }
+}
+
+impl TryFrom<PrimitiveType> for i32 {
+    type Error = PrtError;
+
+    fn try_from(value: PrimitiveType) -> Result<Self, Self::Error> {
+        match value {
+            PrimitiveType::Int(v) => Ok(v),
+            other => Err(type_mismatch("an int", &other)),
+        }
+    }
+}
+
+impl TryFrom<PrimitiveType> for Vec<String> {
+    type Error = PrtError;
+
+    fn try_from(value: PrimitiveType) -> Result<Self, Self::Error> {
+        match value {
+            PrimitiveType::StringArray(v) => Ok(v),
+            other => Err(type_mismatch("a string array", &other)),
+        }
+    }
+}
+
+impl TryFrom<PrimitiveType> for Vec<f64> {
+    type Error = PrtError;
+
+    fn try_from(value: PrimitiveType) -> Result<Self, Self::Error> {
+        match value {
+            PrimitiveType::FloatArray(v) => Ok(v),
+            other => Err(type_mismatch("a float array", &other)),
+        }
+    }
+}
+
+impl TryFrom<PrimitiveType> for Vec<bool> {
+    type Error = PrtError;
+
+    fn try_from(value: PrimitiveType) -> Result<Self, Self::Error> {
+        match value {
+            PrimitiveType::BoolArray(v) => Ok(v),
+            other => Err(type_mismatch("a bool array", &other)),
+        }
+    }
+}
+
+impl TryFrom<PrimitiveType> for Vec<i32> {
+    type Error = PrtError;
+
+    fn try_from(value: PrimitiveType) -> Result<Self, Self::Error> {
+        match value {
+            PrimitiveType::IntArray(v) => Ok(v),
+            other => Err(type_mismatch("an int array", &other)),
+        }
+    }
+}
+
+impl TryFrom<PrimitiveType> for Array2D<String> {
+    type Error = PrtError;
+
+    fn try_from(value: PrimitiveType) -> Result<Self, Self::Error> {
+        match value {
+            PrimitiveType::StringArray2D(v) => Ok(v),
+            other => Err(type_mismatch("a 2D string array", &other)),
+        }
+    }
+}
+
+impl TryFrom<PrimitiveType> for Array2D<f64> {
+    type Error = PrtError;
+
+    fn try_from(value: PrimitiveType) -> Result<Self, Self::Error> {
+        match value {
+            PrimitiveType::FloatArray2D(v) => Ok(v),
+            other => Err(type_mismatch("a 2D float array", &other)),
+        }
+    }
+}
+
+impl TryFrom<PrimitiveType> for Array2D<bool> {
+    type Error = PrtError;
+
+    fn try_from(value: PrimitiveType) -> Result<Self, Self::Error> {
+        match value {
+            PrimitiveType::BoolArray2D(v) => Ok(v),
+            other => Err(type_mismatch("a 2D bool array", &other)),
+        }
+    }
+}
+
+impl TryFrom<PrimitiveType> for Array2D<i32> {
+    type Error = PrtError;
+
+    fn try_from(value: PrimitiveType) -> Result<Self, Self::Error> {
+        match value {
+            PrimitiveType::IntArray2D(v) => Ok(v),
+            other => Err(type_mismatch("a 2D int array", &other)),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+mod json {
+    use serde_json::Value;
+
+    use crate::prt::{Array2D, PrimitiveType, PrtError, Status};
+
+    fn rows_to_json<T: Into<Value>>(array: Array2D<T>) -> Value {
+        let columns = array.columns();
+        let mut values = array.into_values().into_iter();
+        let rows: Vec<Value> = (0..values.len().checked_div(columns).unwrap_or(0))
+            .map(|_| Value::Array(values.by_ref().take(columns).map(Into::into).collect()))
+            .collect();
+        Value::Array(rows)
+    }
+
+    /// Undefined becomes `null`, 2D arrays become arrays of rows.
+    impl From<PrimitiveType> for Value {
+        fn from(value: PrimitiveType) -> Self {
+            match value {
+                PrimitiveType::Undefined() => Value::Null,
+                PrimitiveType::String(v) => v.into(),
+                PrimitiveType::Float(v) => v.into(),
+                PrimitiveType::Bool(v) => v.into(),
+                PrimitiveType::Int(v) => v.into(),
+                PrimitiveType::StringArray(v) => v.into(),
+                PrimitiveType::FloatArray(v) => v.into(),
+                PrimitiveType::BoolArray(v) => v.into(),
+                PrimitiveType::IntArray(v) => v.into(),
+                PrimitiveType::StringArray2D(v) => rows_to_json(v),
+                PrimitiveType::FloatArray2D(v) => rows_to_json(v),
+                PrimitiveType::BoolArray2D(v) => rows_to_json(v),
+                PrimitiveType::IntArray2D(v) => rows_to_json(v),
+            }
+        }
+    }
+
+    fn illegal_json(message: String) -> PrtError {
+        PrtError { message, status: Some(Status::STATUS_ILLEGAL_VALUE) }
+    }
+
+    fn as_int(value: &Value) -> Option<i32> {
+        value.as_i64().and_then(|v| i32::try_from(v).ok())
+    }
+
+    // converts the elements of a flat array, numbers become ints if all of them are integers in the i32 range
+    fn from_json_array(values: &[Value]) -> Result<PrimitiveType, PrtError> {
+        if values.iter().all(Value::is_string) {
+            return Ok(PrimitiveType::StringArray(values.iter().map(|v| v.as_str().unwrap().to_string()).collect()));
+        }
+        if values.iter().all(Value::is_boolean) {
+            return Ok(PrimitiveType::BoolArray(values.iter().map(|v| v.as_bool().unwrap()).collect()));
+        }
+        if values.iter().all(|v| as_int(v).is_some()) {
+            return Ok(PrimitiveType::IntArray(values.iter().map(|v| as_int(v).unwrap()).collect()));
+        }
+        if values.iter().all(Value::is_number) {
+            return Ok(PrimitiveType::FloatArray(values.iter().map(|v| v.as_f64().unwrap()).collect()));
+        }
+        Err(illegal_json(format!("Array elements must all be strings, bools or numbers: {:?}", values)))
+    }
+
+    fn from_json_rows(rows: &[Value]) -> Result<PrimitiveType, PrtError> {
+        let rows: Vec<&Vec<Value>> = rows.iter().map(|r| r.as_array().unwrap()).collect();
+        if rows.iter().any(|r| r.len() != rows[0].len()) {
+            return Err(illegal_json("The rows of a 2D array must have the same length".to_string()));
+        }
+        let values: Vec<Value> = rows.iter().flat_map(|r| r.iter().cloned()).collect();
+        let row_count = rows.len();
+        let to_2d = |flat| -> Result<PrimitiveType, PrtError> {
+            Ok(match flat {
+                PrimitiveType::StringArray(v) => PrimitiveType::StringArray2D(Array2D::new(v, row_count).unwrap()),
+                PrimitiveType::BoolArray(v) => PrimitiveType::BoolArray2D(Array2D::new(v, row_count).unwrap()),
+                PrimitiveType::IntArray(v) => PrimitiveType::IntArray2D(Array2D::new(v, row_count).unwrap()),
+                PrimitiveType::FloatArray(v) => PrimitiveType::FloatArray2D(Array2D::new(v, row_count).unwrap()),
+                other => other,
+            })
+        };
+        to_2d(from_json_array(&values)?)
+    }
+
+    /// `null` becomes Undefined, integers in the i32 range become ints and all other numbers floats.
+    /// Empty arrays become float arrays, arrays of equally long arrays become 2D arrays.
+    impl TryFrom<Value> for PrimitiveType {
+        type Error = PrtError;
+
+        fn try_from(value: Value) -> Result<Self, Self::Error> {
+            match value {
+                Value::Null => Ok(PrimitiveType::Undefined()),
+                Value::Bool(v) => Ok(PrimitiveType::Bool(v)),
+                Value::String(v) => Ok(PrimitiveType::String(v)),
+                Value::Number(ref n) => match as_int(&value) {
+                    Some(v) => Ok(PrimitiveType::Int(v)),
+                    None => n.as_f64().map(PrimitiveType::Float)
+                        .ok_or_else(|| illegal_json(format!("Number {} is out of range", n))),
+                },
+                Value::Array(values) if values.is_empty() => Ok(PrimitiveType::FloatArray(Vec::new())),
+                Value::Array(values) if values.iter().all(Value::is_array) => from_json_rows(&values),
+                Value::Array(values) => from_json_array(&values),
+                Value::Object(_) => Err(illegal_json(format!("Objects cannot be converted: {}", value))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn primitive_type_conversions() {
+        assert_eq!(PrimitiveType::from("red"), PrimitiveType::String("red".to_string()));
+        assert_eq!(PrimitiveType::from(vec![1.0, 2.0]), PrimitiveType::FloatArray(vec![1.0, 2.0]));
+        assert_eq!(PrimitiveType::from(None::<i32>), PrimitiveType::Undefined());
+
+        assert_eq!(f64::try_from(PrimitiveType::Int(3)).unwrap(), 3.0);
+        assert!(bool::try_from(PrimitiveType::Bool(true)).unwrap());
+        let err = i32::try_from(PrimitiveType::Float(1.5)).unwrap_err();
+        assert_eq!(err.status, Some(Status::STATUS_ILLEGAL_VALUE));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn primitive_type_json_values() {
+        use serde_json::json;
+
+        let matrix = PrimitiveType::IntArray2D(Array2D::new(vec![1, 2, 3, 4], 2).unwrap());
+        assert_eq!(serde_json::Value::from(matrix.clone()), json!([[1, 2], [3, 4]]));
+        assert_eq!(PrimitiveType::try_from(json!([[1, 2], [3, 4]])).unwrap(), matrix);
+        assert_eq!(PrimitiveType::try_from(json!(2)).unwrap(), PrimitiveType::Int(2));
+        assert_eq!(PrimitiveType::try_from(json!(2.5)).unwrap(), PrimitiveType::Float(2.5));
+        assert_eq!(PrimitiveType::try_from(json!([1, 2.5])).unwrap(), PrimitiveType::FloatArray(vec![1.0, 2.5]));
+        assert!(PrimitiveType::try_from(json!([[1], [2, 3]])).is_err());
+        assert!(PrimitiveType::try_from(json!({"a": 1})).is_err());
+    }
+}
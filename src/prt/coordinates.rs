@@ -0,0 +1,213 @@
+//! Conversion of GIS footprints (e.g. Z-up, in projected CRS units with large offsets) into the local Y-up frame
+//! PRT works in, and of the generated geometry back into world coordinates.
+
+use crate::prt::{CapturedMesh, FaceHoles, InitialShapeBuilder};
+
+/// The up axis of world coordinates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AxisConvention {
+    /// x east, y north, z up, as in most GIS data.
+    #[default]
+    ZUp,
+    /// x east, y up, z south, the PRT convention.
+    YUp,
+}
+
+impl AxisConvention {
+    pub(crate) fn up_axis(self) -> usize {
+        match self {
+            AxisConvention::ZUp => 2,
+            AxisConvention::YUp => 1,
+        }
+    }
+}
+
+/// Maps world coordinates to the local PRT frame: the origin is subtracted, then Z-up is swapped to Y-up
+/// (north becomes -z). Keep it to map generated geometry back, see [`LocalFrame::mesh_to_world`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LocalFrame {
+    pub axis_convention: AxisConvention,
+    /// In world coordinates.
+    pub origin: [f64; 3],
+}
+
+impl LocalFrame {
+    pub fn new(axis_convention: AxisConvention, origin: [f64; 3]) -> LocalFrame {
+        LocalFrame { axis_convention, origin }
+    }
+
+    /// A frame with the origin in the horizontal center of the bounding box of the polygons, at elevation zero.
+    pub fn centered<P: RingPoint>(axis_convention: AxisConvention, polygons: &[FootprintPolygon<P>]) -> LocalFrame {
+        let points = polygons.iter()
+            .flat_map(|p| p.exterior.iter().chain(p.interiors.iter().flatten()))
+            .map(|p| p.to_xyz_in(axis_convention));
+        let (min, max) = points.fold(([f64::MAX; 3], [f64::MIN; 3]), |(min, max), p| {
+            (std::array::from_fn(|i| min[i].min(p[i])), std::array::from_fn(|i| max[i].max(p[i])))
+        });
+        if min[0] > max[0] {
+            return LocalFrame::new(axis_convention, [0.0; 3]);
+        }
+        let mut origin: [f64; 3] = std::array::from_fn(|i| (min[i] + max[i]) / 2.0);
+        origin[axis_convention.up_axis()] = 0.0;
+        LocalFrame::new(axis_convention, origin)
+    }
+
+    pub fn to_local(&self, world: [f64; 3]) -> [f64; 3] {
+        let [x, y, z] = std::array::from_fn(|i| world[i] - self.origin[i]);
+        self.to_local_vector([x, y, z])
+    }
+
+    pub fn to_world(&self, local: [f64; 3]) -> [f64; 3] {
+        let world = self.to_world_vector(local);
+        std::array::from_fn(|i| world[i] + self.origin[i])
+    }
+
+    /// Rotates a direction (e.g. a normal) without applying the origin offset.
+    pub fn to_local_vector(&self, [x, y, z]: [f64; 3]) -> [f64; 3] {
+        match self.axis_convention {
+            AxisConvention::ZUp => [x, z, -y],
+            AxisConvention::YUp => [x, y, z],
+        }
+    }
+
+    pub fn to_world_vector(&self, [x, y, z]: [f64; 3]) -> [f64; 3] {
+        match self.axis_convention {
+            AxisConvention::ZUp => [x, -z, y],
+            AxisConvention::YUp => [x, y, z],
+        }
+    }
+
+    /// Maps the vertex coordinates and normals of a captured mesh back to world coordinates.
+    pub fn mesh_to_world(&self, mesh: &mut CapturedMesh) {
+        for vertex in mesh.vertex_coords.chunks_exact_mut(3) {
+            vertex.copy_from_slice(&self.to_world([vertex[0], vertex[1], vertex[2]]));
+        }
+        for normal in mesh.normals.chunks_exact_mut(3) {
+            normal.copy_from_slice(&self.to_world_vector([normal[0], normal[1], normal[2]]));
+        }
+    }
+}
+
+/// A point of a ring, 2D points are at elevation zero.
+pub trait RingPoint: Copy {
+    /// The coordinates in the axis convention of the footprint, i.e. 2D points become (x, y, 0) for
+    /// [`AxisConvention::ZUp`] and (x, 0, y) for [`AxisConvention::YUp`].
+    fn to_xyz_in(self, axis_convention: AxisConvention) -> [f64; 3];
+}
+
+impl RingPoint for [f64; 2] {
+    fn to_xyz_in(self, axis_convention: AxisConvention) -> [f64; 3] {
+        match axis_convention {
+            AxisConvention::ZUp => [self[0], self[1], 0.0],
+            AxisConvention::YUp => [self[0], 0.0, self[1]],
+        }
+    }
+}
+
+impl RingPoint for [f64; 3] {
+    fn to_xyz_in(self, _axis_convention: AxisConvention) -> [f64; 3] {
+        self
+    }
+}
+
+/// A polygon in world coordinates, the rings may be closed (the last point repeating the first one) or open.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FootprintPolygon<P> {
+    pub exterior: Vec<P>,
+    pub interiors: Vec<Vec<P>>,
+}
+
+impl<P> FootprintPolygon<P> {
+    pub fn new(exterior: Vec<P>, interiors: Vec<Vec<P>>) -> FootprintPolygon<P> {
+        FootprintPolygon { exterior, interiors }
+    }
+}
+
+// the (twice the) signed area seen from above in the local Y-up frame, positive if counter-clockwise
+fn up_area(ring: &[[f64; 3]]) -> f64 {
+    ring.iter().zip(ring.iter().cycle().skip(1))
+        .map(|(p, q)| (p[2] - q[2]) * (p[0] + q[0]))
+        .sum()
+}
+
+impl InitialShapeBuilder {
+    /// Sets the geometry from polygons in world coordinates: every exterior ring becomes a face and every
+    /// interior ring a hole of it. The rings are mapped into `frame` and their winding is fixed, exterior
+    /// rings face up and holes down. The frame is kept with the initial shape.
+    pub fn footprints<P: RingPoint>(&mut self, polygons: &[FootprintPolygon<P>], frame: LocalFrame) -> &mut Self {
+        let mut vertex_coords = Vec::new();
+        let mut indices = Vec::new();
+        let mut face_counts = Vec::new();
+        let mut holes = Vec::new();
+
+        let mut add_ring = |ring: &[P], face_up: bool| {
+            let mut local: Vec<[f64; 3]> = ring.iter()
+                .map(|p| frame.to_local(p.to_xyz_in(frame.axis_convention)))
+                .collect();
+            if local.len() > 1 && local.first() == local.last() {
+                local.pop();
+            }
+            if (up_area(&local) > 0.0) != face_up {
+                local.reverse();
+            }
+            let first_index = (vertex_coords.len() / 3) as u32;
+            indices.extend(first_index..first_index + local.len() as u32);
+            vertex_coords.extend(local.iter().flatten());
+            face_counts.push(local.len() as u32);
+            face_counts.len() as u32 - 1
+        };
+
+        for polygon in polygons {
+            let face = add_ring(&polygon.exterior, true);
+            let hole_faces: Vec<u32> = polygon.interiors.iter().map(|ring| add_ring(ring, false)).collect();
+            if !hole_faces.is_empty() {
+                holes.push(FaceHoles { face, holes: hole_faces });
+            }
+        }
+
+        self.vertex_coords(vertex_coords)
+            .indices(indices)
+            .face_counts(face_counts)
+            .holes(holes)
+            .local_frame(Some(frame))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prt::tests::lot_builder;
+
+    #[test]
+    fn initial_shape_from_footprints() {
+        let origin = [2_600_000.0, 1_200_000.0, 0.0];
+        // clockwise exterior (seen from above) with a closing point and a counter-clockwise hole
+        let polygon = FootprintPolygon::new(
+            vec![[2_600_000.0, 1_200_000.0], [2_600_000.0, 1_200_010.0], [2_600_010.0, 1_200_010.0],
+                 [2_600_010.0, 1_200_000.0], [2_600_000.0, 1_200_000.0]],
+            vec![vec![[2_600_004.0, 1_200_004.0], [2_600_006.0, 1_200_004.0], [2_600_006.0, 1_200_006.0]]]);
+        let frame = LocalFrame::new(AxisConvention::ZUp, origin);
+        assert_eq!(LocalFrame::centered(AxisConvention::ZUp, std::slice::from_ref(&polygon)).origin,
+                   [2_600_005.0, 1_200_005.0, 0.0]);
+
+        let shape = lot_builder("lot")
+            .footprints(&[polygon], frame)
+            .random_seed(0)
+            .build()
+            .unwrap();
+        assert_eq!(shape.face_counts, vec![4, 3]);
+        assert_eq!(shape.holes, vec![FaceHoles { face: 0, holes: vec![1] }]);
+        // reversed to counter-clockwise, y (north) became -z
+        assert_eq!(shape.vertex_coords[..12], [10.0, 0.0, -0.0, 10.0, 0.0, -10.0, 0.0, 0.0, -10.0, 0.0, 0.0, -0.0]);
+        assert_eq!(shape.validate(), Ok(()));
+        assert_eq!(shape.local_frame(), Some(&frame));
+
+        let mut mesh = CapturedMesh { vertex_coords: vec![10.0, 3.0, -10.0], normals: vec![0.0, 1.0, 0.0],
+                                      ..Default::default() };
+        frame.mesh_to_world(&mut mesh);
+        assert_eq!(mesh.vertex_coords, vec![2_600_010.0, 1_200_010.0, 3.0]);
+        assert_eq!(mesh.normals, vec![0.0, -0.0, 1.0]);
+    }
+}
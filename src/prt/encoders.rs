@@ -0,0 +1,286 @@
+//! Typed options for the encoders bundled with PRT.
+//!
+//! Each options struct knows the id of its encoder and converts into the generic [`EncoderOptions`],
+//! options which are not set are left to the encoder defaults. Use `extra_options` for any option
+//! which is not covered by the typed fields, an extra option wins over a typed field with the same key.
+
+use derive_builder::Builder;
+
+use crate::prt::{EncoderOptions, PrimitiveType};
+
+pub const OBJ_ENCODER_ID: &str = "com.esri.prt.codecs.OBJEncoder";
+pub const GLTF_ENCODER_ID: &str = "com.esri.prt.codecs.GLTFEncoder";
+pub const FBX_ENCODER_ID: &str = "com.esri.prt.codecs.FBXEncoder";
+pub const COLLADA_ENCODER_ID: &str = "com.esri.prt.codecs.DAEEncoder";
+pub const USD_ENCODER_ID: &str = "com.esri.prt.codecs.USDEncoder";
+pub const I3S_ENCODER_ID: &str = "com.esri.prt.codecs.I3SEncoder";
+pub const CITYGML_ENCODER_ID: &str = "com.esri.prt.codecs.CityGMLEncoder";
+pub const ALEMBIC_ENCODER_ID: &str = "com.esri.prt.codecs.AlembicEncoder";
+pub const CGA_REPORT_ENCODER_ID: &str = "com.esri.prt.core.CGAReportEncoder";
+pub const ATTRIBUTE_EVAL_ENCODER_ID: &str = "com.esri.prt.core.AttributeEvalEncoder";
+/// The encoder of this crate which passes the meshes to [`crate::prt::Callbacks::on_mesh`]. Initial shapes which
+/// fail to generate (or are canceled) pass no meshes. Its `captureCallbacks` option is set by generate.
+pub const GEOMETRY_CAPTURE_ENCODER_ID: &str = "com.prust.codecs.GeometryCaptureEncoder";
+
+/// Typed options of a specific encoder.
+pub trait EncoderSettings {
+    const ENCODER_ID: &'static str;
+
+    fn into_encoder_options(self) -> EncoderOptions;
+
+    /// The encoder id and options as expected by [`crate::prt::generate`].
+    fn into_encoder_setup(self) -> (String, EncoderOptions)
+        where Self: Sized
+    {
+        (Self::ENCODER_ID.to_string(), self.into_encoder_options())
+    }
+}
+
+fn set_string(options: &mut EncoderOptions, key: &str, value: Option<String>) {
+    if let Some(v) = value {
+        options.insert(key.to_string(), PrimitiveType::String(v));
+    }
+}
+
+fn set_bool(options: &mut EncoderOptions, key: &str, value: Option<bool>) {
+    if let Some(v) = value {
+        options.insert(key.to_string(), PrimitiveType::Bool(v));
+    }
+}
+
+fn set_offset(options: &mut EncoderOptions, value: Option<[f64; 3]>) {
+    if let Some(v) = value {
+        options.insert("globalOffset".to_string(), PrimitiveType::FloatArray(v.to_vec()));
+    }
+}
+
+fn merge_extra(mut options: EncoderOptions, extra_options: EncoderOptions) -> EncoderOptions {
+    options.extend(extra_options);
+    options
+}
+
+#[derive(Debug, Default, Builder, PartialEq)]
+#[builder(default, pattern = "owned")]
+pub struct ObjEncoderOptions {
+    /// Base name of the written files.
+    #[builder(setter(into, strip_option))]
+    base_name: Option<String>,
+    /// Offset added to all vertex coordinates.
+    #[builder(setter(strip_option))]
+    global_offset: Option<[f64; 3]>,
+    #[builder(setter(strip_option))]
+    triangulate: Option<bool>,
+    #[builder(setter(strip_option))]
+    merge_vertices: Option<bool>,
+    #[builder(setter(strip_option))]
+    create_texture_atlases: Option<bool>,
+    extra_options: EncoderOptions,
+}
+
+impl EncoderSettings for ObjEncoderOptions {
+    const ENCODER_ID: &'static str = OBJ_ENCODER_ID;
+
+    fn into_encoder_options(self) -> EncoderOptions {
+        let mut options = EncoderOptions::new();
+        set_string(&mut options, "baseName", self.base_name);
+        set_offset(&mut options, self.global_offset);
+        set_bool(&mut options, "triangulate", self.triangulate);
+        set_bool(&mut options, "mergeVertices", self.merge_vertices);
+        set_bool(&mut options, "createTextureAtlases", self.create_texture_atlases);
+        merge_extra(options, self.extra_options)
+    }
+}
+
+// the scene encoders share their options, each gets a struct of its own to know its encoder id
+macro_rules! scene_encoder_options {
+    ($(#[$meta:meta])* $name:ident, $encoder_id:expr) => {
+        $(#[$meta])*
+        #[derive(Debug, Default, Builder, PartialEq)]
+        #[builder(default, pattern = "owned")]
+        pub struct $name {
+            #[builder(setter(into, strip_option))]
+            base_name: Option<String>,
+            #[builder(setter(strip_option))]
+            global_offset: Option<[f64; 3]>,
+            #[builder(setter(strip_option))]
+            create_texture_atlases: Option<bool>,
+            extra_options: EncoderOptions,
+        }
+
+        impl EncoderSettings for $name {
+            const ENCODER_ID: &'static str = $encoder_id;
+
+            fn into_encoder_options(self) -> EncoderOptions {
+                let mut options = EncoderOptions::new();
+                set_string(&mut options, "baseName", self.base_name);
+                set_offset(&mut options, self.global_offset);
+                set_bool(&mut options, "createTextureAtlases", self.create_texture_atlases);
+                merge_extra(options, self.extra_options)
+            }
+        }
+    };
+}
+
+scene_encoder_options!(GltfEncoderOptions, GLTF_ENCODER_ID);
+scene_encoder_options!(FbxEncoderOptions, FBX_ENCODER_ID);
+scene_encoder_options!(
+    /// Options of the Collada (DAE) encoder.
+    ColladaEncoderOptions, COLLADA_ENCODER_ID
+);
+
+#[derive(Debug, Default, Builder, PartialEq)]
+#[builder(default, pattern = "owned")]
+pub struct UsdEncoderOptions {
+    #[builder(setter(into, strip_option))]
+    base_name: Option<String>,
+    #[builder(setter(strip_option))]
+    global_offset: Option<[f64; 3]>,
+    extra_options: EncoderOptions,
+}
+
+impl EncoderSettings for UsdEncoderOptions {
+    const ENCODER_ID: &'static str = USD_ENCODER_ID;
+
+    fn into_encoder_options(self) -> EncoderOptions {
+        let mut options = EncoderOptions::new();
+        set_string(&mut options, "baseName", self.base_name);
+        set_offset(&mut options, self.global_offset);
+        merge_extra(options, self.extra_options)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum I3sSceneType {
+    Local,
+    Global,
+}
+
+/// Options of the I3S encoder which writes a scene layer package (SLPK) with a single layer.
+#[derive(Debug, Default, Builder, PartialEq)]
+#[builder(default, pattern = "owned")]
+pub struct I3sEncoderOptions {
+    #[builder(setter(into, strip_option))]
+    base_name: Option<String>,
+    #[builder(setter(strip_option))]
+    scene_type: Option<I3sSceneType>,
+    /// Well-known id of the spatial reference of the scene, e.g. `3857`.
+    #[builder(setter(into, strip_option))]
+    scene_wkid: Option<String>,
+    #[builder(setter(into, strip_option))]
+    layer_name: Option<String>,
+    #[builder(setter(strip_option))]
+    layer_texture_max_dimension: Option<i32>,
+    extra_options: EncoderOptions,
+}
+
+impl EncoderSettings for I3sEncoderOptions {
+    const ENCODER_ID: &'static str = I3S_ENCODER_ID;
+
+    fn into_encoder_options(self) -> EncoderOptions {
+        let mut options = EncoderOptions::new();
+        set_string(&mut options, "baseName", self.base_name);
+        set_string(&mut options, "sceneType", self.scene_type.map(|t| match t {
+            I3sSceneType::Local => "Local".to_string(),
+            I3sSceneType::Global => "Global".to_string(),
+        }));
+        set_string(&mut options, "sceneWkid", self.scene_wkid);
+        // the layer options are arrays with one entry per layer
+        if let Some(name) = self.layer_name {
+            options.insert("layerName".to_string(), PrimitiveType::StringArray(vec![name]));
+        }
+        if let Some(max_dimension) = self.layer_texture_max_dimension {
+            options.insert("layerTextureMaxDimension".to_string(), PrimitiveType::IntArray(vec![max_dimension]));
+        }
+        merge_extra(options, self.extra_options)
+    }
+}
+
+#[derive(Debug, Default, Builder, PartialEq)]
+#[builder(default, pattern = "owned")]
+pub struct CityGmlEncoderOptions {
+    #[builder(setter(into, strip_option))]
+    base_name: Option<String>,
+    #[builder(setter(strip_option))]
+    global_offset: Option<[f64; 3]>,
+    extra_options: EncoderOptions,
+}
+
+impl EncoderSettings for CityGmlEncoderOptions {
+    const ENCODER_ID: &'static str = CITYGML_ENCODER_ID;
+
+    fn into_encoder_options(self) -> EncoderOptions {
+        let mut options = EncoderOptions::new();
+        set_string(&mut options, "baseName", self.base_name);
+        set_offset(&mut options, self.global_offset);
+        merge_extra(options, self.extra_options)
+    }
+}
+
+#[derive(Debug, Default, Builder, PartialEq)]
+#[builder(default, pattern = "owned")]
+pub struct AlembicEncoderOptions {
+    #[builder(setter(into, strip_option))]
+    base_name: Option<String>,
+    #[builder(setter(strip_option))]
+    global_offset: Option<[f64; 3]>,
+    extra_options: EncoderOptions,
+}
+
+impl EncoderSettings for AlembicEncoderOptions {
+    const ENCODER_ID: &'static str = ALEMBIC_ENCODER_ID;
+
+    fn into_encoder_options(self) -> EncoderOptions {
+        let mut options = EncoderOptions::new();
+        set_string(&mut options, "baseName", self.base_name);
+        set_offset(&mut options, self.global_offset);
+        merge_extra(options, self.extra_options)
+    }
+}
+
+/// Options of the encoder which passes the CGA reports to the `cgaReport*` callbacks.
+#[derive(Debug, Default, Builder, PartialEq)]
+#[builder(default, pattern = "owned")]
+pub struct CgaReportEncoderOptions {
+    extra_options: EncoderOptions,
+}
+
+impl EncoderSettings for CgaReportEncoderOptions {
+    const ENCODER_ID: &'static str = CGA_REPORT_ENCODER_ID;
+
+    fn into_encoder_options(self) -> EncoderOptions {
+        self.extra_options
+    }
+}
+
+/// Options of the encoder which passes the final rule attribute values to the `attr*` callbacks.
+#[derive(Debug, Default, Builder, PartialEq)]
+#[builder(default, pattern = "owned")]
+pub struct AttributeEvalEncoderOptions {
+    extra_options: EncoderOptions,
+}
+
+impl EncoderSettings for AttributeEvalEncoderOptions {
+    const ENCODER_ID: &'static str = ATTRIBUTE_EVAL_ENCODER_ID;
+
+    fn into_encoder_options(self) -> EncoderOptions {
+        self.extra_options
+    }
+}
+
+#[derive(Debug, Default, Builder, PartialEq)]
+#[builder(default, pattern = "owned")]
+pub struct GeometryCaptureEncoderOptions {
+    #[builder(setter(strip_option))]
+    triangulate: Option<bool>,
+    extra_options: EncoderOptions,
+}
+
+impl EncoderSettings for GeometryCaptureEncoderOptions {
+    const ENCODER_ID: &'static str = GEOMETRY_CAPTURE_ENCODER_ID;
+
+    fn into_encoder_options(self) -> EncoderOptions {
+        let mut options = EncoderOptions::new();
+        set_bool(&mut options, "triangulate", self.triangulate);
+        merge_extra(options, self.extra_options)
+    }
+}
@@ -0,0 +1,138 @@
+//! Interoperability with [`geo_types`] (feature `geo`): polygons become initial shape geometry and captured
+//! meshes convert back to footprints, e.g. for spatial joins of generated results.
+
+use geo_types::{Coord, LineString, MultiPolygon, Polygon, Rect};
+
+use crate::prt::coordinates::{AxisConvention, FootprintPolygon, LocalFrame};
+use crate::prt::{CapturedMesh, InitialShapeBuilder};
+
+/// A face is considered horizontal if the vertical component of its unit normal is larger than this.
+const HORIZONTAL_TOLERANCE: f64 = 0.999;
+
+fn ring_points(ring: &LineString<f64>) -> Vec<[f64; 2]> {
+    ring.coords().map(|c| [c.x, c.y]).collect()
+}
+
+impl From<&Polygon<f64>> for FootprintPolygon<[f64; 2]> {
+    fn from(polygon: &Polygon<f64>) -> Self {
+        FootprintPolygon::new(ring_points(polygon.exterior()), polygon.interiors().iter().map(ring_points).collect())
+    }
+}
+
+impl From<&FootprintPolygon<[f64; 2]>> for Polygon<f64> {
+    fn from(polygon: &FootprintPolygon<[f64; 2]>) -> Self {
+        let ring = |points: &Vec<[f64; 2]>| LineString::from(points.clone());
+        Polygon::new(ring(&polygon.exterior), polygon.interiors.iter().map(ring).collect())
+    }
+}
+
+impl InitialShapeBuilder {
+    /// Sets the geometry from a `Polygon` or `MultiPolygon` in world coordinates, see
+    /// [`InitialShapeBuilder::footprints`]. The coordinates are x and y of `frame`'s axis convention.
+    pub fn geo_footprints<G: Into<MultiPolygon<f64>>>(&mut self, geometry: G, frame: LocalFrame) -> &mut Self {
+        let polygons: Vec<FootprintPolygon<[f64; 2]>> = geometry.into().iter().map(FootprintPolygon::from).collect();
+        self.footprints(&polygons, frame)
+    }
+}
+
+// the horizontal world coordinates of a point in the local frame, the inverse of the 2D ring mapping
+fn to_world_2d(frame: &LocalFrame, local: [f64; 3]) -> Coord<f64> {
+    let world = frame.to_world(local);
+    match frame.axis_convention {
+        AxisConvention::ZUp => Coord { x: world[0], y: world[1] },
+        AxisConvention::YUp => Coord { x: world[0], y: world[2] },
+    }
+}
+
+impl CapturedMesh {
+    fn local_vertex(&self, index: u32) -> [f64; 3] {
+        let i = index as usize * 3;
+        [self.vertex_coords[i], self.vertex_coords[i + 1], self.vertex_coords[i + 2]]
+    }
+
+    /// The horizontal faces at the lowest elevation of the mesh (e.g. the ground floor of a building) as
+    /// counter-clockwise polygons in the world coordinates of `frame`. The faces are not merged.
+    pub fn footprint(&self, frame: &LocalFrame) -> MultiPolygon<f64> {
+        let min_height = self.vertex_coords.chunks_exact(3).map(|v| v[1]).fold(f64::MAX, f64::min);
+        let extent = self.vertex_coords.iter().fold(0.0, |e: f64, c| e.max(c.abs()));
+        let height_tolerance = 1e-6 * (1.0 + extent);
+
+        let faces = self.face_counts.iter().scan(0usize, |start, &count| {
+            let face = self.vertex_indices.get(*start..*start + count as usize);
+            *start += count as usize;
+            face
+        });
+        let polygons = faces
+            .filter(|face| face.len() >= 3)
+            .map(|face| face.iter().map(|&i| self.local_vertex(i)).collect::<Vec<[f64; 3]>>())
+            .filter(|points| points.iter().all(|p| (p[1] - min_height).abs() <= height_tolerance))
+            .filter(|points| {
+                // Newell's method, only the vertical component matters
+                let [mut nx, mut ny, mut nz] = [0.0; 3];
+                for (p, q) in points.iter().zip(points.iter().cycle().skip(1)) {
+                    nx += (p[1] - q[1]) * (p[2] + q[2]);
+                    ny += (p[2] - q[2]) * (p[0] + q[0]);
+                    nz += (p[0] - q[0]) * (p[1] + q[1]);
+                }
+                let length = (nx * nx + ny * ny + nz * nz).sqrt();
+                length > 0.0 && ny.abs() / length > HORIZONTAL_TOLERANCE
+            })
+            .map(|points| {
+                let mut ring: Vec<Coord<f64>> = points.iter().map(|&p| to_world_2d(frame, p)).collect();
+                let area: f64 = ring.iter().zip(ring.iter().cycle().skip(1)).map(|(p, q)| p.x * q.y - q.x * p.y).sum();
+                if area < 0.0 {
+                    ring.reverse();
+                }
+                Polygon::new(LineString::new(ring), vec![])
+            })
+            .collect();
+        MultiPolygon::new(polygons)
+    }
+
+    /// The horizontal bounding rectangle of the mesh in the world coordinates of `frame`, `None` if it is empty.
+    pub fn bounding_rect(&self, frame: &LocalFrame) -> Option<Rect<f64>> {
+        let mut coords = self.vertex_coords.chunks_exact(3).map(|v| to_world_2d(frame, [v[0], v[1], v[2]]));
+        let first = coords.next()?;
+        let (min, max) = coords.fold((first, first), |(min, max), c| {
+            (Coord { x: min.x.min(c.x), y: min.y.min(c.y) }, Coord { x: max.x.max(c.x), y: max.y.max(c.y) })
+        });
+        Some(Rect::new(min, max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prt::tests::lot_builder;
+
+    #[test]
+    fn initial_shape_from_geo_polygon() {
+        use geo_types::{coord, polygon};
+
+        let parcel = polygon![(x: 100.0, y: 200.0), (x: 110.0, y: 200.0),
+                              (x: 110.0, y: 210.0), (x: 100.0, y: 210.0)];
+        let frame = LocalFrame::new(AxisConvention::ZUp, [100.0, 200.0, 0.0]);
+        let shape = lot_builder("lot")
+            .geo_footprints(parcel.clone(), frame)
+            .random_seed(0)
+            .build()
+            .unwrap();
+        assert_eq!(shape.face_counts, vec![4]);
+        assert_eq!(shape.validate(), Ok(()));
+
+        // a box from the local ground (y = 0) up to y = 3, the bottom face is the footprint
+        let mesh = CapturedMesh {
+            vertex_coords: shape.vertex_coords.iter().copied()
+                .chain(shape.vertex_coords.chunks(3).flat_map(|v| [v[0], 3.0, v[2]]))
+                .collect(),
+            face_counts: vec![4, 4, 4],
+            vertex_indices: vec![3, 2, 1, 0, 4, 5, 6, 7, 0, 1, 5, 4],
+            ..Default::default()
+        };
+        let footprint = mesh.footprint(&frame);
+        assert_eq!(footprint.0.len(), 1);
+        assert_eq!(footprint.0[0].exterior().coords().count(), 5);
+        assert!(footprint.0[0].exterior().coords().all(|c| parcel.exterior().coords().any(|p| p == c)));
+        assert_eq!(mesh.bounding_rect(&frame).unwrap().min(), coord! { x: 100.0, y: 200.0 });
+    }
+}
@@ -0,0 +1,256 @@
+//! Loads the polygon features of a GeoJSON FeatureCollection as initial shapes (feature `geojson`).
+
+use std::{collections, fs, io, path};
+
+use derive_builder::Builder;
+use geojson::{Feature, GeoJson};
+use serde_json::Value;
+
+use crate::prt::coordinates::{AxisConvention, FootprintPolygon, LocalFrame, RingPoint};
+use crate::prt::{InitialShape, InitialShapeBuilder, KeyOrUri, PrimitiveType, PrtError, SeedStrategy, Status};
+
+/// The type a feature property is coerced into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttributeType {
+    String,
+    Float,
+    Bool,
+    Int,
+}
+
+/// Maps a feature property onto a rule attribute, missing and `null` properties keep the rule default.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AttributeMapping {
+    pub property: String,
+    /// The qualified attribute name, e.g. "Default$height".
+    pub attribute: String,
+    pub attribute_type: AttributeType,
+}
+
+impl AttributeMapping {
+    pub fn new(property: &str, attribute: &str, attribute_type: AttributeType) -> AttributeMapping {
+        AttributeMapping { property: property.to_string(), attribute: attribute.to_string(), attribute_type }
+    }
+
+    // numbers and bools are accepted for strings and vice versa, e.g. "12.5" for a float
+    fn coerce(&self, value: &Value) -> Option<PrimitiveType> {
+        let as_bool = |s: &str| match s.to_ascii_lowercase().as_str() {
+            "true" | "yes" | "1" => Some(true),
+            "false" | "no" | "0" => Some(false),
+            _ => None,
+        };
+        match (self.attribute_type, value) {
+            (AttributeType::String, Value::String(s)) => Some(PrimitiveType::String(s.clone())),
+            (AttributeType::String, Value::Number(_) | Value::Bool(_)) =>
+                Some(PrimitiveType::String(value.to_string())),
+            (AttributeType::Float, Value::Number(n)) => n.as_f64().map(PrimitiveType::Float),
+            (AttributeType::Float, Value::String(s)) => s.trim().parse().ok().map(PrimitiveType::Float),
+            (AttributeType::Float, Value::Bool(b)) => Some(PrimitiveType::Float(if *b { 1.0 } else { 0.0 })),
+            (AttributeType::Int, Value::Number(n)) => n.as_i64()
+                .or_else(|| n.as_f64().filter(|f| f.fract() == 0.0).map(|f| f as i64))
+                .and_then(|i| i32::try_from(i).ok())
+                .map(PrimitiveType::Int),
+            (AttributeType::Int, Value::String(s)) => s.trim().parse().ok().map(PrimitiveType::Int),
+            (AttributeType::Int, Value::Bool(b)) => Some(PrimitiveType::Int(*b as i32)),
+            (AttributeType::Bool, Value::Bool(b)) => Some(PrimitiveType::Bool(*b)),
+            (AttributeType::Bool, Value::Number(n)) => n.as_f64().map(|f| PrimitiveType::Bool(f != 0.0)),
+            (AttributeType::Bool, Value::String(s)) => as_bool(s.trim()).map(PrimitiveType::Bool),
+            _ => None,
+        }
+    }
+}
+
+/// Creates one initial shape per feature with a `Polygon` or `MultiPolygon` geometry, other features are skipped.
+/// The name comes from the id property (or the feature id, or the feature index) and the random seed from the
+/// seed strategy, by default a stable hash of the name, i.e. it does not change if the features are reordered.
+/// Features without an id are named `feature_{index}`, a name strategy falls back to the geometry strategy with
+/// the same salt for them, as their names change with the order.
+#[derive(Builder, Clone, Debug)]
+#[builder(pattern = "owned")]
+pub struct GeoJsonLoader {
+    rule_file: KeyOrUri,
+    start_rule: String,
+    #[builder(default, setter(into, strip_option))]
+    id_property: Option<String>,
+    #[builder(default)]
+    attribute_mappings: Vec<AttributeMapping>,
+    /// The frame for all shapes, defaults to a Z-up frame centered on all features.
+    #[builder(default, setter(strip_option))]
+    frame: Option<LocalFrame>,
+    #[builder(default = "SeedStrategy::name(\"\")")]
+    seed_strategy: SeedStrategy,
+}
+
+fn invalid_geojson(message: String) -> PrtError {
+    PrtError { message, status: Some(Status::STATUS_ILLEGAL_VALUE) }
+}
+
+// 2D positions are at elevation zero, the elevation of 3D positions goes onto the up axis of the frame
+fn to_polygon(feature_index: usize, rings: &[Vec<Vec<f64>>], axis_convention: AxisConvention)
+              -> Result<FootprintPolygon<[f64; 3]>, PrtError> {
+    let mut rings = rings.iter().map(|ring| ring.iter()
+        .map(|position| match position[..] {
+            [x, y] => Ok([x, y].to_xyz_in(axis_convention)),
+            [x, y, z, ..] => {
+                let mut point = [x, y].to_xyz_in(axis_convention);
+                point[axis_convention.up_axis()] = z;
+                Ok(point)
+            }
+            _ => Err(invalid_geojson(format!("Feature {}: position with less than two coordinates", feature_index))),
+        })
+        .collect::<Result<Vec<[f64; 3]>, PrtError>>());
+    let exterior = rings.next().unwrap_or(Ok(Vec::new()))?;
+    Ok(FootprintPolygon::new(exterior, rings.collect::<Result<_, _>>()?))
+}
+
+impl GeoJsonLoader {
+    pub fn load<P: AsRef<path::Path>>(&self, geojson_path: P) -> Result<Vec<Box<InitialShape>>, PrtError> {
+        let geojson_path = geojson_path.as_ref();
+        let text = fs::read_to_string(geojson_path).map_err(|e| PrtError {
+            message: format!("Could not read '{}': {}", geojson_path.display(), e),
+            status: Some(match e.kind() {
+                io::ErrorKind::NotFound => Status::STATUS_FILE_NOT_FOUND,
+                _ => Status::STATUS_FILE_READ_FAILED,
+            }),
+        })?;
+        self.parse(&text)
+    }
+
+    /// Parses a FeatureCollection (or a single Feature).
+    pub fn parse(&self, geojson: &str) -> Result<Vec<Box<InitialShape>>, PrtError> {
+        let features = match geojson.parse::<GeoJson>() {
+            Ok(GeoJson::FeatureCollection(collection)) => collection.features,
+            Ok(GeoJson::Feature(feature)) => vec![feature],
+            Ok(GeoJson::Geometry(_)) => return Err(invalid_geojson("Expected features, got a geometry".to_string())),
+            Err(e) => return Err(invalid_geojson(format!("Invalid GeoJSON: {}", e))),
+        };
+
+        let axis_convention = self.frame.map_or(AxisConvention::ZUp, |frame| frame.axis_convention);
+        let mut polygon_features = Vec::new();
+        for (index, feature) in features.iter().enumerate() {
+            let polygons = match feature.geometry.as_ref().map(|g| &g.value) {
+                Some(geojson::Value::Polygon(rings)) => vec![to_polygon(index, rings, axis_convention)?],
+                Some(geojson::Value::MultiPolygon(polygons)) =>
+                    polygons.iter().map(|rings| to_polygon(index, rings, axis_convention)).collect::<Result<_, _>>()?,
+                _ => continue,
+            };
+            polygon_features.push((index, feature, polygons));
+        }
+
+        let frame = self.frame.unwrap_or_else(|| {
+            let all_polygons: Vec<FootprintPolygon<[f64; 3]>> = polygon_features.iter()
+                .flat_map(|(_, _, polygons)| polygons.iter().cloned())
+                .collect();
+            LocalFrame::centered(AxisConvention::ZUp, &all_polygons)
+        });
+
+        polygon_features.iter()
+            .map(|(index, feature, polygons)| self.to_initial_shape(*index, feature, polygons, frame).map(Box::new))
+            .collect()
+    }
+
+    fn to_initial_shape(&self, index: usize, feature: &Feature, polygons: &[FootprintPolygon<[f64; 3]>],
+                        frame: LocalFrame) -> Result<InitialShape, PrtError> {
+        let property = |name: &str| feature.properties.as_ref().and_then(|p| p.get(name)).filter(|v| !v.is_null());
+
+        let id = self.id_property.as_deref().and_then(property).cloned()
+            .or_else(|| feature.id.as_ref().map(|id| match id {
+                geojson::feature::Id::String(s) => Value::String(s.clone()),
+                geojson::feature::Id::Number(n) => Value::Number(n.clone()),
+            }));
+        let (name, seed_strategy) = match (id, &self.seed_strategy) {
+            (Some(Value::String(s)), seed_strategy) => (s, seed_strategy.clone()),
+            (Some(value), seed_strategy) => (value.to_string(), seed_strategy.clone()),
+            (None, SeedStrategy::Name { salt }) => (format!("feature_{}", index), SeedStrategy::geometry(salt)),
+            (None, seed_strategy) => (format!("feature_{}", index), seed_strategy.clone()),
+        };
+
+        let mut attributes = collections::HashMap::new();
+        for mapping in &self.attribute_mappings {
+            let Some(value) = property(&mapping.property) else { continue };
+            let converted = mapping.coerce(value).ok_or_else(|| invalid_geojson(format!(
+                "Feature {} ('{}'): property '{}' cannot be converted to {:?}: {}",
+                index, name, mapping.property, mapping.attribute_type, value)))?;
+            attributes.insert(mapping.attribute.clone(), converted);
+        }
+
+        InitialShapeBuilder::default()
+            .footprints(polygons, frame)
+            .rule_file(self.rule_file.clone())
+            .start_rule(self.start_rule.clone())
+            .seed_strategy(seed_strategy)
+            .name(name.clone())
+            .attributes(attributes)
+            .build()
+            .map_err(|e| invalid_geojson(format!("Feature {} ('{}'): {}", index, name, e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn initial_shapes_from_geojson() {
+        let collection = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {"parcel_id": "A-1", "height": "12.5", "floors": 3},
+             "geometry": {"type": "Polygon", "coordinates": [[[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]]]}},
+            {"type": "Feature", "id": 7, "properties": {"height": null},
+             "geometry": {"type": "MultiPolygon", "coordinates": [[[[20, 0], [30, 0], [30, 10], [20, 0]]]]}},
+            {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [0, 0]}}
+        ]}"#;
+        let loader = GeoJsonLoaderBuilder::default()
+            .rule_file(KeyOrUri::Key("bin/rule.cgb".to_string()))
+            .start_rule("Default$Lot".to_string())
+            .id_property("parcel_id")
+            .attribute_mappings(vec![AttributeMapping::new("height", "Default$height", AttributeType::Float),
+                                     AttributeMapping::new("floors", "Default$floors", AttributeType::String)])
+            .build()
+            .unwrap();
+        let shapes = loader.parse(collection).unwrap();
+        assert_eq!(shapes.len(), 2);
+        assert_eq!(shapes[0].name, "A-1");
+        assert_eq!(shapes[1].name, "7");
+        assert_eq!(shapes[0].attributes["Default$height"], PrimitiveType::Float(12.5));
+        assert_eq!(shapes[0].attributes["Default$floors"], PrimitiveType::from("3"));
+        assert!(shapes[1].attributes.is_empty());
+        assert_eq!(shapes[0].local_frame().unwrap().origin, [15.0, 5.0, 0.0]);
+        // changing the id of the second feature only changes its own seed
+        let renumbered = loader.parse(&collection.replacen("\"id\": 7", "\"id\": 8", 1)).unwrap();
+        assert_eq!((renumbered[0].name.as_str(), renumbered[1].name.as_str()), ("A-1", "8"));
+        assert_eq!(renumbered[0].random_seed, shapes[0].random_seed);
+        assert_ne!(renumbered[1].random_seed, shapes[1].random_seed);
+
+        // without ids the names follow the order, the seeds do not
+        let first = r#"{"type": "Feature", "properties": {},
+            "geometry": {"type": "Polygon", "coordinates": [[[0, 0], [10, 0], [10, 10], [0, 0]]]}}"#;
+        let second = first.replace("10, 10", "10, 20");
+        let features = |a: &str, b: &str| format!(r#"{{"type": "FeatureCollection", "features": [{a}, {b}]}}"#);
+        let shapes = loader.parse(&features(first, &second)).unwrap();
+        let reordered = loader.parse(&features(&second, first)).unwrap();
+        assert_eq!((shapes[0].name.as_str(), reordered[0].name.as_str()), ("feature_0", "feature_0"));
+        assert_eq!(reordered[1].random_seed, shapes[0].random_seed);
+        assert_ne!(shapes[1].random_seed, shapes[0].random_seed);
+
+        // the positions go through the axis convention of the frame
+        let y_up = GeoJsonLoaderBuilder::default()
+            .rule_file(KeyOrUri::Key("bin/rule.cgb".to_string()))
+            .start_rule("Default$Lot".to_string())
+            .frame(LocalFrame::new(AxisConvention::YUp, [0.0; 3]))
+            .build()
+            .unwrap();
+        let square = r#"{"type": "Feature", "properties": {},
+            "geometry": {"type": "Polygon", "coordinates": [[[0, 0, 5], [10, 0, 5], [10, 10, 5], [0, 10, 5]]]}}"#;
+        let shapes = y_up.parse(square).unwrap();
+        assert!(shapes[0].vertex_coords.chunks(3).all(|p| p[1] == 5.0), "{:?}", shapes[0].vertex_coords);
+        assert_eq!(shapes[0].vertex_coords.chunks(3).map(|p| p[2]).fold(f64::MIN, f64::max), 10.0);
+
+        let invalid = collection.replace("\"12.5\"", "\"tall\"");
+        let err = loader.parse(&invalid).err().unwrap();
+        assert_eq!(err.message, "Feature 0 ('A-1'): property 'height' cannot be converted to Float: \"tall\"");
+
+        let missing = std::env::temp_dir().join("prust_missing.geojson");
+        assert_eq!(loader.load(missing).err().unwrap().status, Some(Status::STATUS_FILE_NOT_FOUND));
+        assert_eq!(loader.load(std::env::temp_dir()).err().unwrap().status, Some(Status::STATUS_FILE_READ_FAILED));
+    }
+}
@@ -0,0 +1,132 @@
+//! Deterministic random seeds from a stable hash, they do not depend on the platform, the Rust version or the
+//! batch order.
+
+use crate::prt::LocalFrame;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Coordinates are rounded to this resolution before hashing, to ignore floating point noise.
+const COORDINATE_RESOLUTION: f64 = 1e-3;
+
+// 64 bit FNV-1a, unlike `std::hash` it is guaranteed to stay the same across runs and machines
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Fnv1a {
+        Fnv1a(FNV_OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0 = bytes.iter().fold(self.0, |hash, &b| (hash ^ b as u64).wrapping_mul(FNV_PRIME));
+    }
+
+    // the length prefix keeps the boundary, e.g. salt "ab" and name "c" differ from salt "a" and name "bc"
+    fn write_str(&mut self, s: &str) {
+        self.write(&(s.len() as u64).to_le_bytes());
+        self.write(s.as_bytes());
+    }
+
+    // folds the hash into the positive i32 range of the CGA seed
+    fn seed(&self) -> i32 {
+        ((self.0 ^ (self.0 >> 32)) & 0x7fff_ffff) as i32
+    }
+}
+
+/// How [`crate::prt::InitialShapeBuilder::seed_strategy`] derives the random seed of an initial shape.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SeedStrategy {
+    /// A hash of the geometry in world coordinates (see the local frame of the initial shape) and the salt,
+    /// i.e. the same parcel gets the same seed, no matter where it is in the batch.
+    Geometry { salt: String },
+    /// A hash of the initial shape name and the salt.
+    Name { salt: String },
+}
+
+impl SeedStrategy {
+    pub fn geometry(salt: &str) -> SeedStrategy {
+        SeedStrategy::Geometry { salt: salt.to_string() }
+    }
+
+    pub fn name(salt: &str) -> SeedStrategy {
+        SeedStrategy::Name { salt: salt.to_string() }
+    }
+
+    pub(crate) fn seed(&self, vertex_coords: &[f64], face_counts: &[u32], name: &str,
+                       local_frame: Option<&LocalFrame>) -> i32 {
+        let mut hash = Fnv1a::new();
+        match self {
+            SeedStrategy::Geometry { salt } => {
+                hash.write_str(salt);
+                for vertex in vertex_coords.chunks_exact(3) {
+                    let mut vertex = [vertex[0], vertex[1], vertex[2]];
+                    if let Some(frame) = local_frame {
+                        vertex = frame.to_world(vertex);
+                    }
+                    for coordinate in vertex {
+                        // + 0.0 turns -0.0 into 0.0
+                        let rounded = (coordinate / COORDINATE_RESOLUTION).round() + 0.0;
+                        hash.write(&(rounded as i64).to_le_bytes());
+                    }
+                }
+                for face_count in face_counts {
+                    hash.write(&face_count.to_le_bytes());
+                }
+            }
+            SeedStrategy::Name { salt } => {
+                hash.write_str(salt);
+                hash.write_str(name);
+            }
+        }
+        hash.seed()
+    }
+}
+
+// the random seed as given to the builder, resolved when the initial shape is built
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(untagged))]
+pub(crate) enum SeedSource {
+    Value(i32),
+    Strategy(SeedStrategy),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prt::coordinates::{AxisConvention, FootprintPolygon};
+    use crate::prt::InitialShapeBuilderError;
+    use crate::prt::tests::lot_builder;
+
+    #[test]
+    fn initial_shape_seed_strategies() {
+        let parcel = [FootprintPolygon::new(vec![[1000.0, 2000.0], [1010.0, 2000.0], [1010.0, 2010.0]], vec![])];
+        let build = |name: &str, frame: LocalFrame, strategy: SeedStrategy| {
+            lot_builder(name)
+                .seed_strategy(strategy)
+                .footprints(&parcel, frame)
+                .build()
+                .unwrap()
+                .random_seed
+        };
+        let frame = LocalFrame::new(AxisConvention::ZUp, [1000.0, 2000.0, 0.0]);
+        let other_frame = LocalFrame::new(AxisConvention::ZUp, [1005.5, 1999.3, 0.0]);
+
+        let seed = build("a", frame, SeedStrategy::geometry("project"));
+        assert!(seed >= 0);
+        assert_eq!(build("b", other_frame, SeedStrategy::geometry("project")), seed);
+        assert_ne!(build("a", frame, SeedStrategy::geometry("other project")), seed);
+
+        let seed = build("a", frame, SeedStrategy::name("project"));
+        assert_eq!(build("a", other_frame, SeedStrategy::name("project")), seed);
+        assert_ne!(build("b", frame, SeedStrategy::name("project")), seed);
+        assert_ne!(build("bc", frame, SeedStrategy::name("a")), build("c", frame, SeedStrategy::name("ab")));
+
+        let err = lot_builder("a")
+            .vertex_coords(vec![])
+            .indices(vec![])
+            .face_counts(vec![])
+            .build();
+        assert!(matches!(err, Err(InitialShapeBuilderError::UninitializedField("random_seed"))));
+    }
+}
@@ -0,0 +1,303 @@
+//! Builds and parses the (nested) URIs PRT uses to address files, entries of rule packages and zip archives,
+//! inline data and memory blocks, e.g. `rpk:file:/data/my%20rules.rpk!/bin/extrude.cgb`.
+
+use std::{fmt, path, str};
+
+use crate::prt::{PrtError, Status};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PrtUri {
+    /// A file on disk. The path uses forward slashes, Windows drive letters are prefixed with a slash
+    /// (`/C:/data/x.rpk`) and UNC paths start with two slashes (`//server/share/x.rpk`).
+    File { path: String },
+    /// An entry of a rule package, `entry` is relative to its root (e.g. `bin/extrude.cgb`).
+    Rpk { container: Box<PrtUri>, entry: String },
+    /// An entry of a zip archive, `entry` is relative to its root.
+    Zip { container: Box<PrtUri>, entry: String },
+    /// Inline data, always written base64 encoded.
+    Data { media_type: String, data: Vec<u8> },
+    /// A block of memory owned by the caller, the extension selects the decoder (e.g. `obj`).
+    Memory { address: usize, size: usize, extension: String },
+}
+
+impl PrtUri {
+    /// Relative paths are made absolute with the current working directory. Windows paths
+    /// (`C:\data\x.rpk`, `\\server\share\x.rpk`) are accepted on all platforms.
+    pub fn file<P: AsRef<path::Path>>(file_path: P) -> PrtUri {
+        let file_path = file_path.as_ref();
+        let mut normalized = file_path.to_string_lossy().replace('\\', "/");
+        if !is_absolute(&normalized) && let Ok(absolute) = path::absolute(file_path) {
+            normalized = absolute.to_string_lossy().replace('\\', "/");
+        }
+        if has_drive_letter(&normalized) {
+            normalized.insert(0, '/');
+        }
+        PrtUri::File { path: normalized }
+    }
+
+    /// The root of the rule package at `rpk_path`, e.g. to create a [`crate::prt::ResolveMap`].
+    pub fn rpk<P: AsRef<path::Path>>(rpk_path: P) -> PrtUri {
+        PrtUri::rpk_entry(rpk_path, "")
+    }
+
+    /// An entry of the rule package at `rpk_path`, e.g. `PrtUri::rpk_entry(path, "bin/extrude.cgb")`.
+    pub fn rpk_entry<P: AsRef<path::Path>>(rpk_path: P, entry: &str) -> PrtUri {
+        PrtUri::Rpk { container: Box::new(PrtUri::file(rpk_path)), entry: normalize_entry(entry) }
+    }
+
+    /// An entry of the zip archive at `zip_path`.
+    pub fn zip_entry<P: AsRef<path::Path>>(zip_path: P, entry: &str) -> PrtUri {
+        PrtUri::Zip { container: Box::new(PrtUri::file(zip_path)), entry: normalize_entry(entry) }
+    }
+
+    pub fn data(media_type: &str, data: Vec<u8>) -> PrtUri {
+        PrtUri::Data { media_type: media_type.to_string(), data }
+    }
+
+    /// The memory must stay valid as long as PRT may read from the URI (e.g. until generate returns).
+    pub fn memory(data: &[u8], extension: &str) -> PrtUri {
+        PrtUri::Memory { address: data.as_ptr() as usize, size: data.len(), extension: extension.to_string() }
+    }
+
+    /// Another entry of the same rule package or zip archive, `None` for the other schemes.
+    pub fn with_entry(&self, entry: &str) -> Option<PrtUri> {
+        match self {
+            PrtUri::Rpk { container, .. } =>
+                Some(PrtUri::Rpk { container: container.clone(), entry: normalize_entry(entry) }),
+            PrtUri::Zip { container, .. } =>
+                Some(PrtUri::Zip { container: container.clone(), entry: normalize_entry(entry) }),
+            _ => None,
+        }
+    }
+
+    pub fn scheme(&self) -> &'static str {
+        match self {
+            PrtUri::File { .. } => "file",
+            PrtUri::Rpk { .. } => "rpk",
+            PrtUri::Zip { .. } => "zip",
+            PrtUri::Data { .. } => "data",
+            PrtUri::Memory { .. } => "memory",
+        }
+    }
+
+    /// The path of a file URI in the form of the platform, i.e. without the slash before a drive letter.
+    pub fn file_path(&self) -> Option<path::PathBuf> {
+        match self {
+            PrtUri::File { path } if has_drive_letter(path.get(1..).unwrap_or_default()) =>
+                Some(path::PathBuf::from(&path[1..])),
+            PrtUri::File { path } => Some(path::PathBuf::from(path)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PrtUri {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PrtUri::File { path } => write!(f, "file:{}", percent_encode(path)),
+            PrtUri::Rpk { container, entry } => write!(f, "rpk:{}!/{}", container, percent_encode(entry)),
+            PrtUri::Zip { container, entry } => write!(f, "zip:{}!/{}", container, percent_encode(entry)),
+            PrtUri::Data { media_type, data } => write!(f, "data:{};base64,{}", media_type, base64_encode(data)),
+            PrtUri::Memory { address, size, extension } => write!(f, "memory://{:x}/{}.{}", address, size, extension),
+        }
+    }
+}
+
+fn invalid_uri(uri: &str, reason: &str) -> PrtError {
+    PrtError {
+        message: format!("Invalid URI '{}': {}", uri, reason),
+        status: Some(Status::STATUS_INVALID_URI),
+    }
+}
+
+impl str::FromStr for PrtUri {
+    type Err = PrtError;
+
+    fn from_str(uri: &str) -> Result<Self, Self::Err> {
+        let (scheme, rest) = uri.split_once(':').ok_or_else(|| invalid_uri(uri, "missing scheme"))?;
+        match scheme {
+            "file" => {
+                // file:///x has an empty authority, file://server/share/x is a UNC path
+                let encoded = rest.strip_prefix("//").filter(|r| r.starts_with('/')).unwrap_or(rest);
+                let path = percent_decode(encoded).map_err(|reason| invalid_uri(uri, reason))?;
+                if path.is_empty() {
+                    return Err(invalid_uri(uri, "empty path"));
+                }
+                Ok(PrtUri::File { path })
+            }
+            "rpk" | "zip" => {
+                // entries are percent encoded, the last "!/" therefore separates the innermost entry
+                let (container, entry) = rest.rsplit_once("!/").ok_or_else(|| invalid_uri(uri, "missing '!/'"))?;
+                let container = Box::new(container.parse::<PrtUri>()?);
+                let entry = percent_decode(entry).map_err(|reason| invalid_uri(uri, reason))?;
+                Ok(if scheme == "rpk" { PrtUri::Rpk { container, entry } } else { PrtUri::Zip { container, entry } })
+            }
+            "data" => {
+                let (header, payload) = rest.split_once(',').ok_or_else(|| invalid_uri(uri, "missing ','"))?;
+                let (media_type, data) = match header.strip_suffix(";base64") {
+                    Some(media_type) => (media_type, base64_decode(payload)),
+                    None => (header, percent_decode_bytes(payload)),
+                };
+                let data = data.map_err(|reason| invalid_uri(uri, reason))?;
+                Ok(PrtUri::Data { media_type: media_type.to_string(), data })
+            }
+            "memory" => {
+                let parse = || -> Option<PrtUri> {
+                    let (address, file) = rest.strip_prefix("//")?.split_once('/')?;
+                    let (size, extension) = file.split_once('.')?;
+                    Some(PrtUri::Memory {
+                        address: usize::from_str_radix(address, 16).ok()?,
+                        size: size.parse().ok()?,
+                        extension: extension.to_string(),
+                    })
+                };
+                parse().ok_or_else(|| invalid_uri(uri, "expected memory://<address>/<size>.<extension>"))
+            }
+            _ => Err(invalid_uri(uri, "unsupported scheme")),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for PrtUri {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for PrtUri {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let uri = String::deserialize(deserializer)?;
+        uri.parse().map_err(|e: PrtError| serde::de::Error::custom(e.message))
+    }
+}
+
+fn has_drive_letter(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && (bytes.len() == 2 || bytes[2] == b'/')
+}
+
+fn is_absolute(normalized_path: &str) -> bool {
+    normalized_path.starts_with('/') || has_drive_letter(normalized_path)
+}
+
+fn normalize_entry(entry: &str) -> String {
+    entry.replace('\\', "/").trim_start_matches('/').to_string()
+}
+
+// keeps the characters RFC 3986 allows in paths except '!', which separates nested URIs
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for &b in value.as_bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~/:@$&'()*+,;=".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+    encoded
+}
+
+fn percent_decode_bytes(value: &str) -> Result<Vec<u8>, &'static str> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes.get(i + 1..i + 3).and_then(|h| str::from_utf8(h).ok()).ok_or("truncated escape")?;
+            decoded.push(u8::from_str_radix(hex, 16).map_err(|_| "invalid escape")?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(decoded)
+}
+
+fn percent_decode(value: &str) -> Result<String, &'static str> {
+    String::from_utf8(percent_decode_bytes(value)?).map_err(|_| "escapes are not valid UTF-8")
+}
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn base64_decode(encoded: &str) -> Result<Vec<u8>, &'static str> {
+    let encoded = encoded.trim_end_matches('=');
+    let mut decoded = Vec::with_capacity(encoded.len() * 3 / 4);
+    let (mut n, mut bits) = (0u32, 0);
+    for c in encoded.bytes() {
+        let value = BASE64_ALPHABET.iter().position(|&a| a == c).ok_or("invalid base64")?;
+        n = n << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((n >> bits) as u8);
+        }
+    }
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prt_uri_nesting() {
+        let uri = PrtUri::rpk_entry("/data/my rules/städte.rpk", "bin/extrude.cgb");
+        assert_eq!(uri.to_string(), "rpk:file:/data/my%20rules/st%C3%A4dte.rpk!/bin/extrude.cgb");
+        assert_eq!(uri.to_string().parse::<PrtUri>().unwrap(), uri);
+
+        let nested: PrtUri = "rpk:zip:file:/a.zip!/b%21.rpk!/bin/x.cgb".parse().unwrap();
+        assert_eq!(nested, PrtUri::Rpk {
+            container: Box::new(PrtUri::Zip {
+                container: Box::new(PrtUri::File { path: "/a.zip".to_string() }),
+                entry: "b!.rpk".to_string(),
+            }),
+            entry: "bin/x.cgb".to_string(),
+        });
+        assert_eq!(nested.with_entry("assets/x.jpg").unwrap().to_string(),
+                   "rpk:zip:file:/a.zip!/b%21.rpk!/assets/x.jpg");
+        assert!("rpk:file:/a.rpk".parse::<PrtUri>().is_err());
+    }
+
+    #[test]
+    fn prt_uri_windows_paths() {
+        let uri = PrtUri::rpk(r"C:\Program Files\rules.rpk");
+        assert_eq!(uri.to_string(), "rpk:file:/C:/Program%20Files/rules.rpk!/");
+        let file = PrtUri::file(r"\\server\share\x.obj");
+        assert_eq!(file.to_string(), "file://server/share/x.obj");
+        assert_eq!(file.to_string().parse::<PrtUri>().unwrap(), file);
+        assert_eq!(PrtUri::file("D:/x.obj").file_path(), Some(path::PathBuf::from("D:/x.obj")));
+        assert_eq!("file:///home/x.obj".parse::<PrtUri>().unwrap(),
+                   PrtUri::File { path: "/home/x.obj".to_string() });
+    }
+
+    #[test]
+    fn prt_uri_data_and_memory() {
+        let data = PrtUri::data("text/plain", b"hello!".to_vec());
+        assert_eq!(data.to_string(), "data:text/plain;base64,aGVsbG8h");
+        assert_eq!(data.to_string().parse::<PrtUri>().unwrap(), data);
+        assert_eq!("data:text/plain,a%20b".parse::<PrtUri>().unwrap(), PrtUri::data("text/plain", b"a b".to_vec()));
+        assert_eq!("data:,ab=".parse::<PrtUri>().map(|u| u.to_string()).unwrap(), "data:;base64,YWI9");
+
+        let memory = PrtUri::Memory { address: 0xbeef, size: 42, extension: "obj".to_string() };
+        assert_eq!(memory.to_string(), "memory://beef/42.obj");
+        assert_eq!(memory.to_string().parse::<PrtUri>().unwrap(), memory);
+    }
+}
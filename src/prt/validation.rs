@@ -0,0 +1,356 @@
+//! Geometric validation of initial shapes, reports the problems PRT would reject with `STATUS_ILLEGAL_GEOMETRY`
+//! (or silently generate garbage from) per face.
+
+use std::fmt;
+
+use crate::prt::{FaceHoles, InitialShape};
+
+/// Maximum distance of a face vertex from the face plane, relative to the diagonal of the face bounding box.
+const PLANARITY_TOLERANCE: f64 = 1e-3;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GeometryIssue {
+    /// The number of vertex coordinates is not a multiple of three.
+    VertexCoordsNotTriples { count: usize },
+    /// The face counts do not add up to the number of indices.
+    FaceCountsMismatch { face_counts_sum: usize, index_count: usize },
+    IndexOutOfRange { face: usize, index: u32, vertex_count: usize },
+    TooFewVertices { face: usize, count: u32 },
+    /// The vertex at `position` within the face repeats the previous one (the last one for position 0),
+    /// either by index or by coordinates.
+    DuplicateVertex { face: usize, position: usize },
+    /// All vertices of the face are collinear.
+    ZeroArea { face: usize },
+    /// `deviation` is the maximum distance of a vertex from the face plane.
+    NonPlanar { face: usize, deviation: f64 },
+    /// The edges starting at the given positions within the face intersect.
+    SelfIntersection { face: usize, first_edge: usize, second_edge: usize },
+}
+
+impl GeometryIssue {
+    /// The face the issue belongs to, `None` for issues of the whole initial shape.
+    pub fn face(&self) -> Option<usize> {
+        match self {
+            GeometryIssue::VertexCoordsNotTriples { .. } | GeometryIssue::FaceCountsMismatch { .. } => None,
+            GeometryIssue::IndexOutOfRange { face, .. }
+            | GeometryIssue::TooFewVertices { face, .. }
+            | GeometryIssue::DuplicateVertex { face, .. }
+            | GeometryIssue::ZeroArea { face }
+            | GeometryIssue::NonPlanar { face, .. }
+            | GeometryIssue::SelfIntersection { face, .. } => Some(*face),
+        }
+    }
+}
+
+impl fmt::Display for GeometryIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GeometryIssue::VertexCoordsNotTriples { count } =>
+                write!(f, "{} vertex coordinates are not a multiple of 3", count),
+            GeometryIssue::FaceCountsMismatch { face_counts_sum, index_count } =>
+                write!(f, "Face counts add up to {} but there are {} indices", face_counts_sum, index_count),
+            GeometryIssue::IndexOutOfRange { face, index, vertex_count } =>
+                write!(f, "Face {}: index {} is out of range (vertex count is {})", face, index, vertex_count),
+            GeometryIssue::TooFewVertices { face, count } =>
+                write!(f, "Face {}: {} vertices are not enough for a face", face, count),
+            GeometryIssue::DuplicateVertex { face, position } =>
+                write!(f, "Face {}: vertex {} repeats the previous vertex", face, position),
+            GeometryIssue::ZeroArea { face } =>
+                write!(f, "Face {}: all vertices are collinear", face),
+            GeometryIssue::NonPlanar { face, deviation } =>
+                write!(f, "Face {}: not planar, a vertex is {} off the face plane", face, deviation),
+            GeometryIssue::SelfIntersection { face, first_edge, second_edge } =>
+                write!(f, "Face {}: edges {} and {} intersect", face, first_edge, second_edge),
+        }
+    }
+}
+
+impl InitialShape {
+    /// Checks the geometry and returns all problems found, ordered by face.
+    pub fn validate(&self) -> Result<(), Vec<GeometryIssue>> {
+        let mut issues = Vec::new();
+        if !self.vertex_coords.len().is_multiple_of(3) {
+            issues.push(GeometryIssue::VertexCoordsNotTriples { count: self.vertex_coords.len() });
+        }
+        let face_counts_sum: usize = self.face_counts.iter().map(|&c| c as usize).sum();
+        if face_counts_sum != self.indices.len() {
+            issues.push(GeometryIssue::FaceCountsMismatch { face_counts_sum, index_count: self.indices.len() });
+        }
+
+        let vertex_count = self.vertex_coords.len() / 3;
+        for (face, face_indices) in self.faces() {
+            if face_indices.len() < 3 {
+                issues.push(GeometryIssue::TooFewVertices { face, count: face_indices.len() as u32 });
+                continue;
+            }
+            if let Some(&index) = face_indices.iter().find(|&&i| i as usize >= vertex_count) {
+                issues.push(GeometryIssue::IndexOutOfRange { face, index, vertex_count });
+                continue;
+            }
+            let duplicates = self.duplicate_positions(face_indices);
+            issues.extend(duplicates.iter().map(|&position| GeometryIssue::DuplicateVertex { face, position }));
+
+            let points: Vec<[f64; 3]> = face_indices.iter().enumerate()
+                .filter(|(position, _)| !duplicates.contains(position))
+                .map(|(_, &i)| self.vertex(i))
+                .collect();
+            issues.extend(check_polygon(face, &points));
+        }
+
+        if issues.is_empty() { Ok(()) } else { Err(issues) }
+    }
+
+    /// Fixes the trivial problems: removes duplicate consecutive vertices and then faces with fewer than
+    /// three vertices (holes referring to removed faces are dropped). Faces are only removed if the shape has
+    /// no texture face ranges. Returns the problems which have been fixed, see [`InitialShape::validate`]
+    /// for the remaining ones. Nothing is changed if the face counts do not match the indices.
+    pub fn repair(&mut self) -> Vec<GeometryIssue> {
+        let face_counts_sum: usize = self.face_counts.iter().map(|&c| c as usize).sum();
+        if face_counts_sum != self.indices.len() {
+            return Vec::new();
+        }
+
+        let mut fixed = Vec::new();
+        let mut indices = Vec::with_capacity(self.indices.len());
+        let mut face_counts = Vec::with_capacity(self.face_counts.len());
+        let mut uv_indices: Vec<Vec<u32>> = self.uv_sets.iter().map(|_| Vec::new()).collect();
+        let mut uv_face_ranges: Vec<Vec<u32>> = self.uv_sets.iter().map(|_| Vec::new()).collect();
+        let mut uv_offsets = vec![0usize; self.uv_sets.len()];
+        let mut face_map = Vec::with_capacity(self.face_counts.len()); // old face index to new one
+        let keep_all_faces = !self.texture_face_ranges.is_empty();
+
+        let vertex_count = self.vertex_coords.len() / 3;
+        for (face, face_indices) in self.faces() {
+            let duplicates = if face_indices.iter().all(|&i| (i as usize) < vertex_count) {
+                self.duplicate_positions(face_indices)
+            } else {
+                Vec::new()
+            };
+            let kept: Vec<usize> = (0..face_indices.len()).filter(|p| !duplicates.contains(p)).collect();
+            let remove_face = kept.len() < 3 && !keep_all_faces;
+
+            // the uv indices of a face are only adjusted if there is one per vertex
+            for (uv_set_index, uv_set) in self.uv_sets.iter().enumerate() {
+                let Some(&uv_count) = uv_set.face_ranges.get(face) else { continue };
+                let start = uv_offsets[uv_set_index];
+                let face_uv_indices = uv_set.uv_indices.get(start..start + uv_count as usize).unwrap_or_default();
+                uv_offsets[uv_set_index] += uv_count as usize;
+                if remove_face {
+                    continue;
+                }
+                if face_uv_indices.len() == face_indices.len() {
+                    uv_indices[uv_set_index].extend(kept.iter().map(|&p| face_uv_indices[p]));
+                    uv_face_ranges[uv_set_index].push(kept.len() as u32);
+                } else {
+                    uv_indices[uv_set_index].extend_from_slice(face_uv_indices);
+                    uv_face_ranges[uv_set_index].push(uv_count);
+                }
+            }
+
+            fixed.extend(duplicates.iter().map(|&position| GeometryIssue::DuplicateVertex { face, position }));
+            if remove_face {
+                fixed.push(GeometryIssue::TooFewVertices { face, count: kept.len() as u32 });
+                face_map.push(None);
+                continue;
+            }
+            face_map.push(Some(face_counts.len() as u32));
+            indices.extend(kept.iter().map(|&p| face_indices[p]));
+            face_counts.push(kept.len() as u32);
+        }
+
+        if fixed.is_empty() {
+            return fixed;
+        }
+        self.indices = indices;
+        self.face_counts = face_counts;
+        for (uv_set_index, uv_set) in self.uv_sets.iter_mut().enumerate() {
+            if !uv_set.face_ranges.is_empty() {
+                uv_set.uv_indices = std::mem::take(&mut uv_indices[uv_set_index]);
+                uv_set.face_ranges = std::mem::take(&mut uv_face_ranges[uv_set_index]);
+            }
+        }
+        let new_face = |face: u32| face_map.get(face as usize).copied().flatten();
+        self.holes = self.holes.iter()
+            .filter_map(|h| Some(FaceHoles {
+                face: new_face(h.face)?,
+                holes: h.holes.iter().filter_map(|&i| new_face(i)).collect(),
+            }))
+            .filter(|h| !h.holes.is_empty())
+            .collect();
+        fixed
+    }
+
+    // the index ranges of the faces, stops at the first face which exceeds the indices
+    fn faces(&self) -> impl Iterator<Item = (usize, &[u32])> {
+        self.face_counts.iter()
+            .scan(0usize, |start, &count| {
+                let face_indices = self.indices.get(*start..*start + count as usize);
+                *start += count as usize;
+                face_indices
+            })
+            .enumerate()
+    }
+
+    fn vertex(&self, index: u32) -> [f64; 3] {
+        let i = index as usize * 3;
+        [self.vertex_coords[i], self.vertex_coords[i + 1], self.vertex_coords[i + 2]]
+    }
+
+    // positions within the face whose vertex equals the previous kept one, all indices must be in range
+    fn duplicate_positions(&self, face_indices: &[u32]) -> Vec<usize> {
+        if face_indices.is_empty() {
+            return Vec::new();
+        }
+        let same = |a: u32, b: u32| a == b || self.vertex(a) == self.vertex(b);
+        let mut duplicates = Vec::new();
+        let mut previous = face_indices[0];
+        for (position, &index) in face_indices.iter().enumerate().skip(1) {
+            if same(previous, index) {
+                duplicates.push(position);
+            } else {
+                previous = index;
+            }
+        }
+        // the closing edge, the first vertex is reported if it repeats the last kept one
+        if face_indices.len() > duplicates.len() + 1 && same(previous, face_indices[0]) {
+            duplicates.insert(0, 0);
+        }
+        duplicates
+    }
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+// checks the area, planarity and self-intersections of a face without duplicate vertices
+fn check_polygon(face: usize, points: &[[f64; 3]]) -> Vec<GeometryIssue> {
+    if points.len() < 3 {
+        return vec![GeometryIssue::TooFewVertices { face, count: points.len() as u32 }];
+    }
+
+    // Newell's method, robust for concave and slightly non-planar polygons
+    let mut normal = [0.0; 3];
+    for (i, p) in points.iter().enumerate() {
+        let q = points[(i + 1) % points.len()];
+        normal[0] += (p[1] - q[1]) * (p[2] + q[2]);
+        normal[1] += (p[2] - q[2]) * (p[0] + q[0]);
+        normal[2] += (p[0] - q[0]) * (p[1] + q[1]);
+    }
+    // the normal of e.g. a symmetric bowtie cancels out, take the largest triangle of a fan instead
+    if dot(normal, normal) <= f64::EPSILON {
+        normal = points.windows(2).skip(1)
+            .map(|w| cross(sub(w[0], points[0]), sub(w[1], points[0])))
+            .max_by(|a, b| dot(*a, *a).total_cmp(&dot(*b, *b)))
+            .unwrap_or_default();
+    }
+    let length = dot(normal, normal).sqrt();
+    let (min, max) = points.iter().fold(([f64::MAX; 3], [f64::MIN; 3]), |(min, max), p| {
+        ([min[0].min(p[0]), min[1].min(p[1]), min[2].min(p[2])], [max[0].max(p[0]), max[1].max(p[1]), max[2].max(p[2])])
+    });
+    let diagonal = dot(sub(max, min), sub(max, min)).sqrt();
+    if length <= f64::EPSILON * diagonal * diagonal {
+        return vec![GeometryIssue::ZeroArea { face }];
+    }
+    let normal = [normal[0] / length, normal[1] / length, normal[2] / length];
+
+    let mut issues = Vec::new();
+    let centroid = points.iter().fold([0.0; 3], |c, p| [c[0] + p[0], c[1] + p[1], c[2] + p[2]])
+        .map(|c| c / points.len() as f64);
+    let deviation = points.iter().map(|&p| dot(sub(p, centroid), normal).abs()).fold(0.0, f64::max);
+    if deviation > PLANARITY_TOLERANCE * diagonal {
+        issues.push(GeometryIssue::NonPlanar { face, deviation });
+    }
+
+    // project onto the plane of the two axes which are most perpendicular to the normal
+    let drop_axis = (0..3).max_by(|&a, &b| normal[a].abs().total_cmp(&normal[b].abs())).unwrap();
+    let (u, v) = [(1, 2), (0, 2), (0, 1)][drop_axis];
+    let projected: Vec<[f64; 2]> = points.iter().map(|p| [p[u], p[v]]).collect();
+    let n = projected.len();
+    for first in 0..n {
+        // skip the adjacent edges, they share a vertex
+        for second in first + 2..n {
+            if first == 0 && second == n - 1 {
+                continue;
+            }
+            let (a, b) = (projected[first], projected[(first + 1) % n]);
+            let (c, d) = (projected[second], projected[(second + 1) % n]);
+            if segments_intersect(a, b, c, d) {
+                issues.push(GeometryIssue::SelfIntersection { face, first_edge: first, second_edge: second });
+            }
+        }
+    }
+    issues
+}
+
+fn orientation(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> f64 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+fn on_segment(a: [f64; 2], b: [f64; 2], p: [f64; 2]) -> bool {
+    p[0] >= a[0].min(b[0]) && p[0] <= a[0].max(b[0]) && p[1] >= a[1].min(b[1]) && p[1] <= a[1].max(b[1])
+}
+
+// also true if the segments touch or overlap
+fn segments_intersect(a: [f64; 2], b: [f64; 2], c: [f64; 2], d: [f64; 2]) -> bool {
+    let (o1, o2) = (orientation(a, b, c), orientation(a, b, d));
+    let (o3, o4) = (orientation(c, d, a), orientation(c, d, b));
+    if o1 * o2 < 0.0 && o3 * o4 < 0.0 {
+        return true;
+    }
+    (o1 == 0.0 && on_segment(a, b, c)) || (o2 == 0.0 && on_segment(a, b, d))
+        || (o3 == 0.0 && on_segment(c, d, a)) || (o4 == 0.0 && on_segment(c, d, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prt::tests::lot_builder;
+
+    #[test]
+    fn initial_shape_geometry_issues() {
+        let shape = |vertex_coords: Vec<f64>, indices: Vec<u32>, face_counts: Vec<u32>| {
+            lot_builder("lot")
+                .vertex_coords(vertex_coords)
+                .indices(indices)
+                .face_counts(face_counts)
+                .random_seed(0)
+                .build()
+                .unwrap()
+        };
+        let square = vec![0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 1.0, 0.0, 0.0];
+
+        assert_eq!(shape(square.clone(), vec![0, 1, 2, 3], vec![4]).validate(), Ok(()));
+        assert_eq!(shape(square.clone(), vec![0, 1, 2, 4], vec![4]).validate(),
+                   Err(vec![GeometryIssue::IndexOutOfRange { face: 0, index: 4, vertex_count: 4 }]));
+        assert_eq!(shape(square.clone(), vec![0, 1, 2, 3], vec![3]).validate(), Err(vec![
+            GeometryIssue::FaceCountsMismatch { face_counts_sum: 3, index_count: 4 }]));
+        assert_eq!(shape(square.clone(), vec![0, 2, 1, 3], vec![4]).validate(), Err(vec![
+            GeometryIssue::SelfIntersection { face: 0, first_edge: 0, second_edge: 2 }]));
+
+        let mut non_planar = square.clone();
+        non_planar[4] = 0.5;
+        let issues = shape(non_planar, vec![0, 1, 2, 3], vec![4]).validate().unwrap_err();
+        assert!(matches!(issues[..], [GeometryIssue::NonPlanar { face: 0, .. }]));
+
+        // a duplicate vertex and a degenerate second face
+        let mut repairable = shape(square, vec![0, 1, 1, 2, 3, 0, 0, 1], vec![5, 3]);
+        assert_eq!(repairable.validate(), Err(vec![
+            GeometryIssue::DuplicateVertex { face: 0, position: 2 },
+            GeometryIssue::DuplicateVertex { face: 1, position: 1 },
+            GeometryIssue::TooFewVertices { face: 1, count: 2 },
+        ]));
+        assert_eq!(repairable.repair().len(), 3);
+        assert_eq!(repairable.validate(), Ok(()));
+        assert_eq!(repairable.indices, vec![0, 1, 2, 3]);
+        assert_eq!(repairable.face_counts, vec![4]);
+    }
+}
@@ -1,9 +1,8 @@
 use prust::prt;
 use ctor::ctor;
 use lazy_static::lazy_static;
-use prust::prt::KeyOrUri;
-
-// note: these tests only work single threaded ATM
+use prust::prt::{KeyOrUri, PrtUri};
+use std::path;
 
 lazy_static! {
     static ref PRT_CONTEXT: Box<prt::PrtContext> = {
@@ -18,6 +17,24 @@ fn initialize() {
     println!("PRT has been initialized: {}", prt_context.as_ref());
 }
 
+fn test_file(name: &str) -> path::PathBuf {
+    path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join(name)
+}
+
+// a unit square in the ground plane (y = 0) from x to x + 1, generated with the extrude rule
+fn unit_square(x: f64, name: &str) -> prt::InitialShapeBuilder {
+    let mut builder = prt::InitialShapeBuilder::default();
+    builder
+        .vertex_coords(vec![x, 0.0, 0.0, x, 0.0, 1.0, x + 1.0, 0.0, 1.0, x + 1.0, 0.0, 0.0])
+        .indices(vec![0, 1, 2, 3])
+        .face_counts(vec![4])
+        .rule_file(KeyOrUri::Uri(PrtUri::rpk_entry(test_file("extrude.rpk"), "bin/extrude.cgb")))
+        .start_rule("Default$Init".to_string())
+        .random_seed(0)
+        .name(name.to_string());
+    builder
+}
+
 
 #[test]
 fn test_default_log_handler() {
@@ -44,7 +61,8 @@ fn test_custom_log_handler() {
     prt::add_log_handler(&mut custom_log_handler);
     prt::log("capture me", prt::LogLevel::LOG_INFO);
     prt::remove_log_handler(&mut custom_log_handler);
-    assert_eq!("capture me", custom_log_handler.captured_message);
+    // log handlers are global, the handler may also capture the messages of concurrent tests
+    assert!(custom_log_handler.captured_message.contains("capture me"));
 }
 
 #[test]
@@ -52,27 +70,57 @@ fn test_generate() {
     let mut log_handler = Box::new(prt::DefaultLogHandler::default());
     prt::add_log_handler(&mut log_handler);
 
-    let rule_file_uri = KeyOrUri::Uri(format!("rpk:file:{}/tests/extrude.rpk!/bin/extrude.cgb",
-                                env!("CARGO_MANIFEST_DIR")));
+    let initial_shape = Box::new(unit_square(0.0, "rust_shape").build().unwrap());
 
-    let initial_shape = Box::new(prt::InitialShapeBuilder::default()
-        .vertex_coords(vec![0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0, 0.0, 0.0])
-        .indices(vec![0, 1, 2, 3])
-        .face_counts(vec![4])
-        .rule_file(rule_file_uri)
-        .start_rule("Default$Init".to_string())
-        .random_seed(0)
-        .name("rust_shape".to_string())
-        .build().unwrap());
+    let initial_shapes: Vec<Box<prt::InitialShape>> = vec![initial_shape];
+    let encoders = vec!["com.esri.prt.codecs.OBJEncoder".to_string()];
+    let encoder_options = vec![prt::EncoderOptions::default()];
+    let mut callbacks = prt::FileCallbacks::default();
+
+    prt::generate(&initial_shapes, None, &encoders, &encoder_options,
+                  &mut callbacks, None, None).unwrap();
+}
+
+#[test]
+fn test_generate_with_occlusion() {
+    let initial_shapes: Vec<Box<prt::InitialShape>> = (0..2).map(|i| {
+        let x = i as f64 * 2.0;
+        Box::new(unit_square(x, &format!("rust_shape_{}", i)).build().unwrap())
+    }).collect();
+
+    let mut occlusion_set = prt::OcclusionSet::new().unwrap();
+    let cache = prt::Cache::new().unwrap();
+    let generate_options = prt::GenerateOptionsBuilder::default()
+        .occlusion_neighborhood_size(5.0)
+        .build().unwrap();
+    let mut callbacks = prt::FileCallbacks::default();
+    let occlusion_handles = prt::generate_occluders(&initial_shapes, &vec![], &vec![], &mut callbacks,
+                                                    &mut occlusion_set, Some(&generate_options), Some(&cache))
+        .unwrap();
+    assert_eq!(occlusion_handles.len(), initial_shapes.len());
+
+    let encoders = vec!["com.esri.prt.codecs.OBJEncoder".to_string()];
+    let encoder_options = vec![prt::EncoderOptions::default()];
+    prt::generate(&initial_shapes, Some(&occlusion_handles), &encoders, &encoder_options,
+                  &mut callbacks, Some(&occlusion_set), Some(&generate_options)).unwrap();
+}
+
+#[test]
+fn test_generate_with_options() {
+    let initial_shape = Box::new(unit_square(0.0, "rust_shape").build().unwrap());
+
+    let generate_options = prt::GenerateOptionsBuilder::default()
+        .number_worker_threads(1)
+        .cache_shape_tree(false)
+        .build().unwrap();
 
     let initial_shapes: Vec<Box<prt::InitialShape>> = vec![initial_shape];
     let encoders = vec!["com.esri.prt.codecs.OBJEncoder".to_string()];
     let encoder_options = vec![prt::EncoderOptions::default()];
-    let mut callbacks = Box::new(prt::FileCallbacks::default());
+    let mut callbacks = prt::FileCallbacks::default();
 
-    let generate_status = prt::generate(&initial_shapes, &encoders, &encoder_options,
-                                        &mut callbacks);
-    assert_eq!(generate_status, prt::Status::STATUS_OK);
+    prt::generate(&initial_shapes, None, &encoders, &encoder_options,
+                  &mut callbacks, None, Some(&generate_options)).unwrap();
 }
 
 #[test]
@@ -83,4 +131,514 @@ fn test_version() {
     assert_eq!(prt_version.version_major, 3);
     assert_eq!(prt_version.version_minor, 2);
     assert_eq!(prt_version.version_string, "3.2.10650");
-}
\ No newline at end of file
+}
+#[test]
+fn test_generate_canceled() {
+    use prt::encoders::EncoderSettings;
+
+    // cancels as soon as the first initial shape has started
+    #[derive(Default)]
+    struct CancelAfterFirstShape {
+        canceled: bool,
+        started_shapes: Vec<usize>,
+        generated_shapes: std::collections::BTreeSet<usize>,
+    }
+
+    impl prt::Callbacks for CancelAfterFirstShape {
+        fn is_canceled(&self) -> bool {
+            self.canceled
+        }
+
+        fn on_progress(&mut self, event: prt::ProgressEvent) {
+            if event.progress == prt::ShapeProgress::Started {
+                self.started_shapes.push(event.initial_shape_index);
+                self.canceled = true;
+            }
+        }
+
+        fn on_mesh(&mut self, initial_shape_index: usize, _mesh: prt::CapturedMesh) {
+            self.generated_shapes.insert(initial_shape_index);
+        }
+    }
+
+    let initial_shapes: Vec<Box<prt::InitialShape>> = (0..10).map(|i| {
+        let x = i as f64 * 2.0;
+        Box::new(unit_square(x, &format!("rust_shape_{}", i)).build().unwrap())
+    }).collect();
+
+    let (encoder, options) = prt::encoders::GeometryCaptureEncoderOptionsBuilder::default()
+        .build().unwrap()
+        .into_encoder_setup();
+    let encoders = vec![encoder];
+    let encoder_options = vec![options];
+    let generate_options = prt::GenerateOptionsBuilder::default()
+        .number_worker_threads(1)
+        .build().unwrap();
+
+    let mut callbacks = CancelAfterFirstShape::default();
+    let err = prt::generate(&initial_shapes, None, &encoders, &encoder_options,
+                            &mut callbacks, None, Some(&generate_options)).err().unwrap();
+    assert_eq!(err.status, Some(prt::Status::STATUS_CANCELED));
+    // only the shape which has triggered the cancellation has been generated
+    let first_shape = callbacks.started_shapes[0];
+    assert_eq!(callbacks.generated_shapes.iter().copied().collect::<Vec<usize>>(), vec![first_shape]);
+
+    // canceled before generating, nothing is passed to PRT
+    let cancellation_token = prt::CancellationToken::new();
+    let mut callbacks = prt::FileCallbacks::default().with_cancellation_token(cancellation_token.clone());
+    cancellation_token.cancel();
+    let err = prt::generate(&initial_shapes, None, &encoders, &encoder_options,
+                            &mut callbacks, None, None).err().unwrap();
+    assert_eq!(err.status, Some(prt::Status::STATUS_CANCELED));
+}
+
+#[test]
+fn test_generate_progress() {
+    let initial_shapes: Vec<Box<prt::InitialShape>> = (0..3).map(|i| {
+        let x = i as f64 * 2.0;
+        Box::new(unit_square(x, &format!("rust_shape_{}", i)).build().unwrap())
+    }).collect();
+    let encoders = vec!["com.esri.prt.codecs.OBJEncoder".to_string()];
+    let encoder_options = vec![prt::EncoderOptions::default()];
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let mut callbacks = prt::FileCallbacks::default().with_progress_listener(sender);
+    prt::generate(&initial_shapes, None, &encoders, &encoder_options,
+                  &mut callbacks, None, None).unwrap();
+    drop(callbacks);
+
+    let events: Vec<prt::ProgressEvent> = receiver.iter().collect();
+    for i in 0..initial_shapes.len() {
+        let shape_events: Vec<&prt::ProgressEvent> = events.iter()
+            .filter(|e| e.initial_shape_index == i)
+            .collect();
+        assert_eq!(shape_events.len(), 2);
+        assert_eq!(shape_events[0].progress, prt::ShapeProgress::Started);
+        assert_eq!(shape_events[1].progress, prt::ShapeProgress::Finished);
+        assert_eq!(shape_events[1].initial_shape_count, initial_shapes.len());
+    }
+}
+
+#[test]
+fn test_generate_progress_canceled() {
+    use prt::encoders::EncoderSettings;
+
+    // cancels as soon as the first initial shape has started
+    struct CancelingListener {
+        cancellation_token: prt::CancellationToken,
+        sender: std::sync::mpsc::Sender<prt::ProgressEvent>,
+    }
+
+    impl prt::ProgressListener for CancelingListener {
+        fn on_progress(&mut self, event: prt::ProgressEvent) {
+            if event.progress == prt::ShapeProgress::Started {
+                self.cancellation_token.cancel();
+            }
+            let _ = self.sender.send(event);
+        }
+    }
+
+    let initial_shapes: Vec<Box<prt::InitialShape>> = (0..5).map(|i| {
+        let x = i as f64 * 2.0;
+        Box::new(unit_square(x, &format!("rust_shape_{}", i)).build().unwrap())
+    }).collect();
+    let (encoder, options) = prt::encoders::GeometryCaptureEncoderOptionsBuilder::default()
+        .build().unwrap()
+        .into_encoder_setup();
+    let generate_options = prt::GenerateOptionsBuilder::default()
+        .number_worker_threads(1)
+        .build().unwrap();
+
+    let cancellation_token = prt::CancellationToken::new();
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let mut callbacks = prt::FileCallbacks::default()
+        .with_cancellation_token(cancellation_token.clone())
+        .with_progress_listener(CancelingListener { cancellation_token, sender });
+    let err = prt::generate(&initial_shapes, None, &vec![encoder], &vec![options],
+                            &mut callbacks, None, Some(&generate_options)).err().unwrap();
+    assert_eq!(err.status, Some(prt::Status::STATUS_CANCELED));
+    drop(callbacks);
+
+    // the shapes which have not been finished before the cancellation fail with its status
+    let events: Vec<prt::ProgressEvent> = receiver.iter().collect();
+    for i in 0..initial_shapes.len() {
+        let shape_events: Vec<&prt::ProgressEvent> = events.iter()
+            .filter(|e| e.initial_shape_index == i)
+            .collect();
+        assert_eq!(shape_events.len(), 2);
+        assert_eq!(shape_events[0].progress, prt::ShapeProgress::Started);
+        assert_ne!(shape_events[1].progress, prt::ShapeProgress::Started);
+    }
+    assert!(events.iter().any(|e| e.progress == prt::ShapeProgress::Failed(prt::Status::STATUS_CANCELED)));
+}
+
+#[test]
+fn test_generate_captured_geometry() {
+    use prt::encoders::EncoderSettings;
+
+    let initial_shape = Box::new(unit_square(0.0, "rust_shape").build().unwrap());
+    let initial_shapes = vec![initial_shape];
+
+    let (encoder, options) = prt::encoders::GeometryCaptureEncoderOptionsBuilder::default()
+        .triangulate(true)
+        .build().unwrap()
+        .into_encoder_setup();
+    let encoders = vec![encoder];
+    let encoder_options = vec![options];
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let mut callbacks = prt::FileCallbacks::default().with_geometry_consumer(sender);
+    prt::generate(&initial_shapes, None, &encoders, &encoder_options,
+                  &mut callbacks, None, None).unwrap();
+    drop(callbacks);
+
+    let meshes: Vec<(usize, prt::CapturedMesh)> = receiver.iter().collect();
+    assert!(!meshes.is_empty());
+    for (initial_shape_index, mesh) in &meshes {
+        assert_eq!(*initial_shape_index, 0);
+        assert!(mesh.face_count() > 0);
+        assert!(mesh.face_counts.iter().all(|c| *c == 3));
+        assert_eq!(mesh.vertex_indices.len(), mesh.normal_indices.len());
+
+        let material = mesh.material.as_ref().unwrap();
+        assert!((0.0..=1.0).contains(&material.opacity));
+        assert!(material.color.iter().all(|c| (0.0..=1.0).contains(c)));
+        assert!(material.texture("colormap").is_none()); // the extrude rule does not set textures
+    }
+}
+
+#[test]
+fn test_generate_reports() {
+    let initial_shapes: Vec<Box<prt::InitialShape>> = (0..2).map(|i| {
+        let x = i as f64 * 2.0;
+        Box::new(unit_square(x, &format!("rust_shape_{}", i)).build().unwrap())
+    }).collect();
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let mut callbacks = prt::FileCallbacks::default().with_geometry_consumer(sender);
+    let reports = prt::generate_reports(&initial_shapes, &mut callbacks, false, None).unwrap();
+    assert_eq!(reports.len(), initial_shapes.len());
+    // extrude.cgb has no report statements, the collecting of the values is covered by the unit tests
+    assert!(reports.iter().all(|r| r.is_empty()), "{:?}", reports);
+    drop(callbacks);
+    assert_eq!(receiver.iter().count(), 0); // geometry has been skipped
+}
+
+#[test]
+fn test_generate_rejects_invalid_initial_shape() {
+    let initial_shapes: Vec<Box<prt::InitialShape>> = ["rust_shape", "rust\0shape"].iter().map(|name| {
+        Box::new(unit_square(0.0, name).build().unwrap())
+    }).collect();
+
+    let mut callbacks = prt::FileCallbacks::default();
+    let err = prt::generate_reports(&initial_shapes, &mut callbacks, false, None).err().unwrap();
+    assert!(err.message.starts_with("Initial shape 1 "), "{}", err.message);
+    assert!(err.message.ends_with("name contains a NUL byte"), "{}", err.message);
+
+    let err = prt::generate(&initial_shapes, None, &vec![], &vec![], &mut callbacks, None, None).err().unwrap();
+    assert_eq!(err.status, Some(prt::Status::STATUS_ILLEGAL_VALUE));
+
+    let inputs = prt::GenerateInputs { initial_shapes: &initial_shapes, ..Default::default() };
+    let err = prt::try_generate(&inputs, &mut callbacks).err().unwrap();
+    assert_eq!(err.message, "Initial shape 1 ('rust\0shape'): name contains a NUL byte");
+    assert_eq!(err.status, Some(prt::Status::STATUS_ILLEGAL_VALUE));
+}
+
+#[test]
+fn test_generate_attributes() {
+    // the only attribute of extrude.cgb, overridden by the initial shape
+    let initial_shape = Box::new(unit_square(0.0, "rust_shape")
+        .attributes([("Default$streetWidth".to_string(), prt::PrimitiveType::Float(3.5))].into())
+        .build().unwrap());
+    let initial_shapes = vec![initial_shape];
+
+    let mut callbacks = prt::FileCallbacks::default();
+    let attributes = prt::generate_attributes(&initial_shapes, &mut callbacks, None).unwrap();
+    assert_eq!(attributes.len(), 1);
+    assert!(attributes[0].contains_key("Default$streetWidth"), "{:?}", attributes[0]);
+    assert_eq!(attributes[0]["Default$streetWidth"], prt::PrimitiveType::Float(3.5));
+    assert!(attributes[0].values().all(|v| *v != prt::PrimitiveType::Undefined()));
+}
+
+#[test]
+fn test_generate_with_2d_array_attributes() {
+    // extrude.cgb declares no array attributes, i.e. PRT accepts these but does not evaluate them, the conversion
+    // of the array values reported by PRT is covered by the primitive_wrapper_rows unit test
+    let mut attributes = std::collections::HashMap::new();
+    attributes.insert("Default$streetWidth".to_string(), prt::PrimitiveType::Float(3.5));
+    attributes.insert("Default$offsets".to_string(),
+                      prt::PrimitiveType::FloatArray2D(prt::Array2D::new(vec![0.0, 1.0, 2.0, 3.0], 2).unwrap()));
+    attributes.insert("Default$labels".to_string(),
+                      prt::PrimitiveType::StringArray2D(prt::Array2D::new(vec!["a".to_string(), "b".to_string()], 2)
+                          .unwrap()));
+
+    let initial_shape = Box::new(unit_square(0.0, "rust_shape").attributes(attributes).build().unwrap());
+    let initial_shapes = vec![initial_shape];
+
+    let mut callbacks = prt::FileCallbacks::default();
+    let attributes = prt::generate_attributes(&initial_shapes, &mut callbacks, None).unwrap();
+    assert_eq!(attributes.len(), 1);
+    assert_eq!(attributes[0].get("Default$streetWidth"), Some(&prt::PrimitiveType::Float(3.5)));
+}
+
+#[test]
+fn test_resolve_map_read_bytes() {
+    let rpk_uri = PrtUri::rpk(test_file("extrude.rpk"));
+    let resolve_map = prt::ResolveMap::from_uri(&rpk_uri.to_string()).unwrap();
+
+    let rule_bytes = resolve_map.read_bytes("bin/extrude.cgb", None).unwrap();
+    assert!(!rule_bytes.is_empty());
+
+    let missing = resolve_map.read_bytes("assets/does_not_exist.jpg", None);
+    assert!(missing.is_err());
+}
+
+#[test]
+fn test_generate_with_holes() {
+    let rule_file_uri = KeyOrUri::Uri(PrtUri::rpk_entry(test_file("extrude.rpk"), "bin/extrude.cgb"));
+
+    // a square footprint with a square courtyard
+    let initial_shape = Box::new(prt::InitialShapeBuilder::default()
+        .vertex_coords(vec![0.0, 0.0, 0.0, 0.0, 0.0, 3.0, 3.0, 0.0, 3.0, 3.0, 0.0, 0.0,
+                            1.0, 0.0, 1.0, 2.0, 0.0, 1.0, 2.0, 0.0, 2.0, 1.0, 0.0, 2.0])
+        .indices(vec![0, 1, 2, 3, 4, 5, 6, 7])
+        .face_counts(vec![4, 4])
+        .holes(vec![prt::FaceHoles { face: 0, holes: vec![1] }])
+        .rule_file(rule_file_uri)
+        .start_rule("Default$Init".to_string())
+        .random_seed(0)
+        .name("rust_courtyard".to_string())
+        .build().unwrap());
+
+    let initial_shapes: Vec<Box<prt::InitialShape>> = vec![initial_shape];
+    let encoders = vec!["com.esri.prt.codecs.OBJEncoder".to_string()];
+    let encoder_options = vec![prt::EncoderOptions::default()];
+    let mut callbacks = prt::FileCallbacks::default();
+
+    prt::generate(&initial_shapes, None, &encoders, &encoder_options,
+                  &mut callbacks, None, None).unwrap();
+}
+
+#[test]
+fn test_resolve_geometry() {
+    let geometry_uri = KeyOrUri::Uri(PrtUri::file(test_file("footprint.obj")));
+    let rule_file_uri = KeyOrUri::Uri(PrtUri::rpk_entry(test_file("extrude.rpk"), "bin/extrude.cgb"));
+
+    let cache = prt::Cache::new().unwrap();
+    let mut builder = prt::InitialShapeBuilder::default();
+    let resolved = builder.resolve_geometry(&geometry_uri, None, Some(&cache)).unwrap();
+    assert_eq!(resolved.decoder_id.as_deref(), Some("com.esri.prt.codecs.OBJDecoder"));
+
+    let initial_shape = Box::new(builder
+        .rule_file(rule_file_uri)
+        .start_rule("Default$Init".to_string())
+        .random_seed(0)
+        .name("rust_obj_shape".to_string())
+        .build().unwrap());
+
+    let initial_shapes: Vec<Box<prt::InitialShape>> = vec![initial_shape];
+    let encoders = vec!["com.esri.prt.codecs.OBJEncoder".to_string()];
+    let encoder_options = vec![prt::EncoderOptions::default()];
+    let mut callbacks = prt::FileCallbacks::default();
+
+    prt::generate(&initial_shapes, None, &encoders, &encoder_options,
+                  &mut callbacks, None, None).unwrap();
+}
+
+#[test]
+fn test_resolve_geometry_missing_file() {
+    let geometry_uri = KeyOrUri::Uri(PrtUri::file(test_file("does_not_exist.obj")));
+    let mut builder = prt::InitialShapeBuilder::default();
+    let err = builder.resolve_geometry(&geometry_uri, None, None).unwrap_err();
+    assert!(err.status.is_some());
+    assert!(err.message.contains("OBJDecoder"));
+}
+
+#[test]
+fn test_list_codecs() {
+    let encoders = prt::list_encoders().unwrap();
+    let obj_encoder = encoders.iter()
+        .find(|e| e.id == "com.esri.prt.codecs.OBJEncoder")
+        .expect("OBJ encoder is not installed");
+    assert!(obj_encoder.extensions.iter().any(|e| e == "obj"));
+    assert_eq!(obj_encoder.content_types, vec![prt::ContentType::CT_GEOMETRY]);
+
+    let decoders = prt::list_decoders().unwrap();
+    assert!(decoders.iter().any(|d| d.id == "com.esri.prt.codecs.OBJDecoder"));
+
+    let obj_encoder_info = prt::get_encoder_info(&obj_encoder.id).unwrap();
+    assert_eq!(&obj_encoder_info, obj_encoder);
+    assert!(prt::get_encoder_info("com.example.NoSuchEncoder").is_err());
+
+    use prt::encoders::*;
+    for encoder_id in [OBJ_ENCODER_ID, GLTF_ENCODER_ID, FBX_ENCODER_ID, COLLADA_ENCODER_ID, USD_ENCODER_ID,
+                       I3S_ENCODER_ID, CITYGML_ENCODER_ID, ALEMBIC_ENCODER_ID, CGA_REPORT_ENCODER_ID,
+                       ATTRIBUTE_EVAL_ENCODER_ID, GEOMETRY_CAPTURE_ENCODER_ID] {
+        assert!(encoders.iter().any(|e| e.id == encoder_id), "{} is not installed", encoder_id);
+    }
+}
+
+#[test]
+fn test_encoder_options_info() {
+    let options_info = prt::get_encoder_options_info("com.esri.prt.codecs.OBJEncoder").unwrap();
+    let base_name = options_info.iter()
+        .find(|o| o.key == "baseName")
+        .expect("OBJ encoder has no baseName option");
+    assert!(matches!(base_name.default_value, prt::PrimitiveType::String(_)));
+
+    // the annotations of the options, the defaults are within their ranges and enums
+    let enum_option = options_info.iter()
+        .find(|o| !o.enum_values.is_empty())
+        .expect("OBJ encoder has no @Enum option");
+    assert!(enum_option.enum_values.contains(&enum_option.default_value), "{:?}", enum_option);
+    for option in options_info.iter().filter(|o| o.range.is_some()) {
+        let (min, max) = option.range.unwrap();
+        let default = f64::try_from(option.default_value.clone()).unwrap();
+        assert!(min <= default && default <= max, "{:?}", option);
+    }
+
+    let mut options = prt::EncoderOptions::new();
+    options.insert("baseName".to_string(), prt::PrimitiveType::String("validated".to_string()));
+    let validated = prt::validate_encoder_options("com.esri.prt.codecs.OBJEncoder", &options).unwrap();
+    assert_eq!(validated.get("baseName"), Some(&prt::PrimitiveType::String("validated".to_string())));
+    assert!(validated.len() >= options_info.len());
+
+    assert!(prt::get_encoder_options_info("com.example.NoSuchEncoder").is_err());
+}
+
+#[test]
+fn test_batch_generator() {
+    use prt::encoders::EncoderSettings;
+
+    #[derive(Default)]
+    struct MeshCounter {
+        initial_shape_indices: std::collections::BTreeSet<usize>,
+    }
+
+    impl prt::Callbacks for MeshCounter {
+        fn on_mesh(&mut self, initial_shape_index: usize, _mesh: prt::CapturedMesh) {
+            self.initial_shape_indices.insert(initial_shape_index);
+        }
+    }
+
+    let initial_shapes = (0..5).map(|i| {
+        let x = i as f64 * 2.0;
+        Box::new(unit_square(x, &format!("rust_shape_{}", i)).build().unwrap())
+    });
+
+    let (encoder, options) = prt::encoders::GeometryCaptureEncoderOptionsBuilder::default()
+        .build().unwrap()
+        .into_encoder_setup();
+    let batch_generator = prt::BatchGeneratorBuilder::default()
+        .encoders(vec![encoder])
+        .encoder_options(vec![options])
+        .chunk_size(2)
+        .threads(2)
+        .build().unwrap();
+
+    let mut generated_shapes = Vec::new();
+    let mut chunk_indices = Vec::new();
+    batch_generator.generate(initial_shapes, |_| MeshCounter::default(), |chunk| {
+        assert!(chunk.result.is_ok());
+        assert_eq!(chunk.first_shape_index, chunk.chunk_index * 2);
+        assert_eq!(chunk.callbacks.initial_shape_indices.len(), chunk.shape_count);
+        chunk_indices.push(chunk.chunk_index);
+        generated_shapes.extend(chunk.callbacks.initial_shape_indices.iter().map(|i| chunk.first_shape_index + i));
+    }).unwrap();
+
+    chunk_indices.sort();
+    assert_eq!(chunk_indices, vec![0, 1, 2]);
+    generated_shapes.sort();
+    assert_eq!(generated_shapes, (0..5).collect::<Vec<usize>>());
+}
+
+#[test]
+fn test_batch_generator_callbacks_panic() {
+    use std::panic;
+
+    let initial_shapes = (0..4).map(|i| {
+        Box::new(unit_square(0.0, &format!("rust_shape_{}", i)).build().unwrap())
+    });
+
+    let batch_generator = prt::BatchGeneratorBuilder::default()
+        .encoders(vec![])
+        .chunk_size(1)
+        .threads(2)
+        .build().unwrap();
+
+    // the panic of one chunk must neither hang the batch nor stop the other chunks
+    let mut chunk_indices = Vec::new();
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        batch_generator.generate(initial_shapes, |chunk_index| {
+            assert_ne!(chunk_index, 1, "no callbacks for chunk 1");
+            prt::FileCallbacks::default()
+        }, |chunk| chunk_indices.push(chunk.chunk_index))
+    }));
+
+    assert!(result.is_err());
+    chunk_indices.sort();
+    assert_eq!(chunk_indices, vec![0, 2, 3]);
+}
+
+#[test]
+fn test_concurrent_generate() {
+    use prt::encoders::EncoderSettings;
+
+    let output_root = std::env::temp_dir().join(format!("prust_test_concurrent_generate_{}", std::process::id()));
+
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..4).map(|i| {
+            let output_root = &output_root;
+            scope.spawn(move || {
+                // the same name in every thread, but a different position to tell the OBJ files apart
+                let x = i as f64 * 10.0;
+                let initial_shapes = vec![Box::new(unit_square(x, "rust_shape").build().unwrap())];
+
+                let (capture_encoder, capture_options) = prt::encoders::GeometryCaptureEncoderOptionsBuilder::default()
+                    .build().unwrap()
+                    .into_encoder_setup();
+                let job = prt::GenerateJob {
+                    initial_shapes,
+                    encoders: vec!["com.esri.prt.codecs.OBJEncoder".to_string(), capture_encoder],
+                    encoder_options: vec![prt::EncoderOptions::default(), capture_options],
+                    generate_options: None,
+                };
+
+                let output_directory = output_root.join(i.to_string());
+                let (sender, receiver) = std::sync::mpsc::channel();
+                let mut callbacks = prt::FileCallbacks::default()
+                    .with_geometry_consumer(sender)
+                    .with_output_directory(&output_directory);
+                job.generate(&mut callbacks).unwrap();
+                drop(callbacks);
+
+                let mut reports_callbacks = prt::FileCallbacks::default();
+                let reports = prt::generate_reports(&job.initial_shapes, &mut reports_callbacks, false, None).unwrap();
+                assert_eq!(reports.len(), 1);
+                (x, output_directory, receiver.iter().count())
+            })
+        }).collect();
+
+        let results: Vec<_> = workers.into_iter().map(|w| w.join().unwrap()).collect();
+        assert!(results[0].2 > 0);
+        assert!(results.iter().all(|(_, _, mesh_count)| *mesh_count == results[0].2));
+
+        // every call wrote its own OBJ file with the vertices of its own initial shape
+        for (x, output_directory, _) in &results {
+            let obj_files: Vec<_> = std::fs::read_dir(output_directory).unwrap()
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "obj"))
+                .collect();
+            assert_eq!(obj_files.len(), 1, "{:?}", obj_files);
+            let obj = std::fs::read_to_string(&obj_files[0]).unwrap();
+            let vertex_xs: Vec<f64> = obj.lines()
+                .filter_map(|line| line.strip_prefix("v "))
+                .map(|coords| coords.split_whitespace().next().unwrap().parse().unwrap())
+                .collect();
+            assert!(!vertex_xs.is_empty());
+            assert!(vertex_xs.iter().all(|vx| (*x - 1e-6..=*x + 1.0 + 1e-6).contains(vx)), "{:?}", vertex_xs);
+        }
+    });
+
+    std::fs::remove_dir_all(&output_root).unwrap();
+}